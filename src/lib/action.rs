@@ -1,5 +1,19 @@
 use crate::{
-    errors::{ApplicationError, SelectionError}, get_input, todo::Todo, Todos
+    crdt::Replica,
+    errors::{ApplicationError, SelectionError},
+    formats::{
+        self,
+        csv::{self, CsvConfig, Field},
+        Format,
+    },
+    get_input, get_secret_input,
+    query::Filter,
+    scan,
+    snapshot::{restore_todo, SnapshotDiff, SnapshotStore},
+    storage::{json::read_new_passphrase, Storage},
+    template::{Template, Templates},
+    todo::Todo,
+    Todos,
 };
 use std::io::{stdout, Write};
 
@@ -9,13 +23,21 @@ pub enum Action {
     Delete,
     List,
     Complete,
+    ChangePassphrase,
+    Sync,
+    SyncFile,
+    Snapshot,
+    BrowseSnapshots,
+    Import,
+    Export,
+    Scan,
     Exit,
     Invalid,
 }
 
 impl From<String> for Action {
     fn from(mut val: String) -> Self {
-        if val.len() < 1 {
+        if val.is_empty() {
             return Action::Invalid;
         }
 
@@ -34,11 +56,45 @@ impl From<String> for Action {
             "4" => Action::List,
             "5" => Action::Complete,
             "6" => Action::Exit,
+            // Added later, numbered after Exit so the numbers never change
+            "7" => Action::ChangePassphrase,
+            "8" => Action::Sync,
+            "9" => Action::SyncFile,
+            "10" => Action::Snapshot,
+            "11" => Action::BrowseSnapshots,
+            "12" => Action::Import,
+            "13" => Action::Export,
+            "14" => Action::Scan,
             _ => Action::Invalid,
         }
     }
 }
 
+impl Action {
+    /// Whether the action changes the stored data, used to refuse it in read-only mode.
+    /// Snapshots can be browsed read-only, only restoring them is refused.
+    pub fn needs_write_access(&self) -> bool {
+        matches!(
+            self,
+            Action::Create
+                | Action::Edit
+                | Action::Delete
+                | Action::Complete
+                | Action::ChangePassphrase
+                | Action::Sync
+                | Action::SyncFile
+                | Action::Import
+                | Action::Scan
+        )
+    }
+
+    /// Whether the action stores the changes it makes itself, so they must not be
+    /// recorded again afterwards.
+    pub fn stores_changes(&self) -> bool {
+        matches!(self, Action::Sync | Action::SyncFile)
+    }
+}
+
 #[cfg(not(test))]
 fn action_sleep() {
     use core::time;
@@ -47,22 +103,94 @@ fn action_sleep() {
 }
 
 #[cfg(test)]
-fn action_sleep() {
-    ()
-}
+fn action_sleep() {}
 
 fn print_input_label(label: &str) {
     print!("{label}");
     let _ = stdout().flush(); // This is necessary, otherwise the text appears after the next println
 }
 
-fn list_todos_internal<F>(todos: Todos, mut get_input: F) -> Result<(), ApplicationError>
+/// Prints the TODOs numbered from 1 to select them.
+fn print_selection(todos: &[Todo], template: &Template) {
+    println!("Your TODO list:\n");
+    for (i, todo) in todos.iter().enumerate() {
+        println!("# {}: {}", i + 1, template.render(todo));
+    }
+    println!();
+}
+
+/// Shortest start of an id to select a TODO by, so short numbers are never ids
+const MIN_ID_PREFIX: usize = 4;
+
+/// Finds a TODO by its number as printed to select it, or by its id or the start
+/// of it, at least 4 characters long. A selector which is both is refused.
+pub fn find_todo(todos: &[Todo], selector: &str) -> Result<usize, SelectionError> {
+    let number = selector
+        .parse::<usize>()
+        .ok()
+        .filter(|number| !selector.starts_with('0') && (1..=todos.len()).contains(number));
+
+    let prefix = selector.to_lowercase();
+    let found: Vec<usize> = todos
+        .iter()
+        .enumerate()
+        .filter(|(_, todo)| {
+            prefix.len() >= MIN_ID_PREFIX && todo.id.to_string().starts_with(&prefix)
+        })
+        .map(|(i, _)| i)
+        .collect();
+
+    match (number, found.as_slice()) {
+        (Some(number), []) => Ok(number - 1),
+        (Some(number), _) => Err(SelectionError(format!(
+            "{} is TODO number {} and the start of an id, give more of the id",
+            selector, number
+        ))),
+        (None, [i]) => Ok(*i),
+        (None, []) => Err(SelectionError(format!("There is no TODO {}", selector))),
+        (None, _) => Err(SelectionError(format!(
+            "{} matches several TODOs",
+            selector
+        ))),
+    }
+}
+
+/// Removes a TODO, its subtasks move up to its parent.
+pub fn remove_todo(todos: &mut Vec<Todo>, index: usize) -> Todo {
+    let removed = todos.swap_remove(index);
+    for todo in todos
+        .iter_mut()
+        .filter(|todo| todo.parent == Some(removed.id))
+    {
+        todo.parent = removed.parent;
+    }
+    removed
+}
+
+fn list_todos_internal<F>(
+    todos: Todos,
+    template: &Template,
+    mut get_input: F,
+) -> Result<(), ApplicationError>
 where
     F: FnMut() -> Result<String, std::io::Error>,
 {
+    print_input_label("Filter, e.g. status:open and due<+7d (empty for all): ");
+    let input = get_input()?;
+    let filter = match input.trim() {
+        "" => None,
+        source => Some(Filter::parse(source)?),
+    };
+    let today = chrono::Local::now().date_naive();
+
     println!("Your TODO list:\n");
     for todo in todos.borrow().iter() {
-        println!("completed: {} | text: {}", todo.completed, todo.text);
+        if filter
+            .as_ref()
+            .is_none_or(|filter| filter.matches(todo, today))
+        {
+            println!("{}", template.render(todo));
+        }
     }
 
     println!();
@@ -70,8 +198,8 @@ where
     let _ = get_input()?;
     Ok(())
 }
-pub fn list_todos(todos: Todos) -> Result<(), ApplicationError> {
-    list_todos_internal(todos, get_input)
+pub fn list_todos(todos: Todos, template: &Template) -> Result<(), ApplicationError> {
+    list_todos_internal(todos, template, get_input)
 }
 
 fn create_todo_internal<F>(todos: Todos, mut get_input: F) -> Result<(), ApplicationError>
@@ -92,20 +220,15 @@ pub fn create_todo(todos: Todos) -> Result<(), ApplicationError> {
     create_todo_internal(todos, get_input)
 }
 
-fn complete_todo_internal<F>(todos: Todos, mut get_input: F) -> Result<(), ApplicationError>
+fn complete_todo_internal<F>(
+    todos: Todos,
+    template: &Template,
+    mut get_input: F,
+) -> Result<(), ApplicationError>
 where
     F: FnMut() -> Result<String, std::io::Error>,
 {
-    println!("Your TODO list:\n");
-    for (i, todo) in todos.borrow().iter().enumerate() {
-        println!(
-            "# {}: completed: {} | text: {}",
-            i + 1,
-            todo.completed,
-            todo.text
-        );
-    }
-    println!();
+    print_selection(&todos.borrow(), template);
 
     print_input_label("Enter TODO to complete: ");
     let input = get_input()?;
@@ -117,7 +240,7 @@ where
     }
 
     if let Some(todo) = todos.borrow_mut().get_mut(number - 1) {
-        todo.completed = true;
+        todo.set_completed(true, chrono::Local::now().date_naive());
         println!("Successfully marked TODO as completed.");
     } else {
         return Err(ApplicationError(
@@ -126,26 +249,21 @@ where
     }
     
     action_sleep();
-    return Ok(());
+    Ok(())
 }
-pub fn complete_todo(todos: Todos) -> Result<(), ApplicationError> {
-    complete_todo_internal(todos, get_input)
+pub fn complete_todo(todos: Todos, template: &Template) -> Result<(), ApplicationError> {
+    complete_todo_internal(todos, template, get_input)
 }
 
-fn delete_todo_internal<F>(todos: Todos, mut get_input: F) -> Result<(), ApplicationError>
+fn delete_todo_internal<F>(
+    todos: Todos,
+    template: &Template,
+    mut get_input: F,
+) -> Result<(), ApplicationError>
 where
     F: FnMut() -> Result<String, std::io::Error>,
 {
-    println!("Your TODO list:\n");
-    for (i, todo) in todos.borrow().iter().enumerate() {
-        println!(
-            "# {}: completed: {} | text: {}",
-            i + 1,
-            todo.completed,
-            todo.text
-        );
-    }
-    println!();
+    print_selection(&todos.borrow(), template);
 
     print_input_label("Enter TODO to delete: ");
     let input = get_input()?;
@@ -156,30 +274,25 @@ where
         return Err(SelectionError(input).into());
     }
 
-    todos.borrow_mut().swap_remove(number - 1);
+    remove_todo(&mut todos.borrow_mut(), number - 1);
     println!("Successfully delete TODO.");
     action_sleep();
     
-    return Ok(());
+    Ok(())
 }
-pub fn delete_todo(todos: Todos) -> Result<(), ApplicationError> {
-    delete_todo_internal(todos, get_input)
+pub fn delete_todo(todos: Todos, template: &Template) -> Result<(), ApplicationError> {
+    delete_todo_internal(todos, template, get_input)
 }
 
-fn edit_todo_internal<F>(todos: Todos, mut get_input: F) -> Result<(), ApplicationError>
+fn edit_todo_internal<F>(
+    todos: Todos,
+    template: &Template,
+    mut get_input: F,
+) -> Result<(), ApplicationError>
 where
     F: FnMut() -> Result<String, std::io::Error>,
 {
-    println!("Your TODO list:\n");
-    for (i, todo) in todos.borrow().iter().enumerate() {
-        println!(
-            "# {}: completed: {} | text: {}",
-            i + 1,
-            todo.completed,
-            todo.text
-        );
-    }
-    println!();
+    print_selection(&todos.borrow(), template);
 
     print_input_label("Enter TODO to edit: ");
     let input = get_input()?;
@@ -211,7 +324,8 @@ where
         }
 
         "C" | "c" => {
-            todo.completed = !todo.completed;
+            let completed = !todo.completed;
+            todo.set_completed(completed, chrono::Local::now().date_naive());
             println!(
                 "Successfully toggled completed state. New state: {}",
                 todo.completed
@@ -223,17 +337,410 @@ where
     };
 
     action_sleep();
-    return Ok(());
+    Ok(())
+}
+
+pub fn edit_todo(todos: Todos, template: &Template) -> Result<(), ApplicationError> {
+    edit_todo_internal(todos, template, get_input)
+}
+
+fn change_passphrase_internal<F>(
+    storage: &mut dyn Storage,
+    mut get_input: F,
+) -> Result<(), ApplicationError>
+where
+    F: FnMut() -> Result<String, std::io::Error>,
+{
+    let passphrase = read_new_passphrase(&mut get_input)?;
+    storage.change_passphrase(&passphrase)?;
+
+    println!("Successfully changed the passphrase.");
+    action_sleep();
+    Ok(())
+}
+
+pub fn change_passphrase(storage: &mut dyn Storage) -> Result<(), ApplicationError> {
+    change_passphrase_internal(storage, get_secret_input)
+}
+
+fn sync_file_internal<F>(
+    replica: &mut Replica,
+    todos: Todos,
+    mut get_input: F,
+) -> Result<(), ApplicationError>
+where
+    F: FnMut() -> Result<String, std::io::Error>,
+{
+    print_input_label("Enter replica file to sync with: ");
+    let input = get_input()?;
+    let path = std::path::Path::new(&input);
+
+    replica.update(&todos.borrow());
+    // Without a file yet there is nothing to merge, the file is only exported
+    if path.exists() {
+        replica.merge(&Replica::load(path)?);
+    }
+    replica.save(path)?;
+
+    let merged = replica.todos(&todos.borrow());
+    *todos.borrow_mut() = merged;
+
+    println!("Successfully synced with {}.", input);
+    action_sleep();
+    Ok(())
+}
+
+/// Merges the replica file another machine exported, then exports the merged
+/// replica to the same file for the other machine to pick up.
+pub fn sync_file(replica: &mut Replica, todos: Todos) -> Result<(), ApplicationError> {
+    sync_file_internal(replica, todos, get_input)
+}
+
+fn create_snapshot_internal<F>(
+    store: &SnapshotStore,
+    todos: Todos,
+    mut get_input: F,
+) -> Result<(), ApplicationError>
+where
+    F: FnMut() -> Result<String, std::io::Error>,
+{
+    print_input_label("Enter a label for the snapshot: ");
+    let input = get_input()?;
+
+    let snapshot = store.create(&input, &todos.borrow())?;
+    println!(
+        "Successfully created snapshot \"{}\" at {}.",
+        snapshot.label,
+        snapshot.created.format("%Y-%m-%d %H:%M:%S UTC")
+    );
+    action_sleep();
+    Ok(())
+}
+
+pub fn create_snapshot(store: &SnapshotStore, todos: Todos) -> Result<(), ApplicationError> {
+    create_snapshot_internal(store, todos, get_input)
+}
+
+fn print_diff(diff: &SnapshotDiff, template: &Template) {
+    if diff.is_empty() {
+        println!("No differences to the current TODOs.");
+        return;
+    }
+    for todo in &diff.added {
+        println!("+ {}", template.render(todo));
+    }
+    for todo in &diff.removed {
+        println!("- {}", template.render(todo));
+    }
+    for (old, new) in &diff.changed {
+        println!("~ {}", template.render(old));
+        println!("  now: {}", template.render(new));
+    }
+}
+
+fn browse_snapshots_internal<F>(
+    store: &SnapshotStore,
+    todos: Todos,
+    templates: &Templates,
+    read_only: bool,
+    mut get_input: F,
+) -> Result<(), ApplicationError>
+where
+    F: FnMut() -> Result<String, std::io::Error>,
+{
+    let snapshots = store.list()?;
+    if snapshots.is_empty() {
+        return Err(ApplicationError("There are no snapshots yet".to_string()));
+    }
+
+    println!("Your snapshots:\n");
+    for (i, snapshot) in snapshots.iter().enumerate() {
+        println!(
+            "# {}: {} | {} | {} TODO(s)",
+            i + 1,
+            snapshot.created.format("%Y-%m-%d %H:%M:%S UTC"),
+            snapshot.label,
+            snapshot.todos.len()
+        );
+    }
+    println!();
+
+    print_input_label("Enter snapshot to compare: ");
+    let input = get_input()?;
+
+    let number = input.parse::<usize>()?;
+
+    if number == 0 || number > snapshots.len() {
+        return Err(SelectionError(input).into());
+    }
+    let snapshot = &snapshots[number - 1];
+
+    println!("\nChanges since the snapshot:\n");
+    let diff = SnapshotDiff::new(&snapshot.todos, &todos.borrow());
+    print_diff(&diff, &templates.list);
+    println!();
+
+    if read_only {
+        print_input_label("Opened read-only, press enter key to return: ");
+        get_input()?;
+        return Ok(());
+    }
+    print_input_label("[R]estore all, [C]herry-pick TODOs or press enter key to return: ");
+    let input = get_input()?;
+
+    match input.as_str() {
+        "R" | "r" => {
+            *todos.borrow_mut() = snapshot.todos.clone();
+            println!("Successfully restored the snapshot.");
+        }
+        "C" | "c" => {
+            let restorable = diff.restorable();
+            if restorable.is_empty() {
+                return Err(ApplicationError(
+                    "The snapshot has no deleted or changed TODOs".to_string(),
+                ));
+            }
+            for (i, todo) in restorable.iter().enumerate() {
+                println!("# {}: {}", i + 1, templates.select.render(todo));
+            }
+            print_input_label("Enter TODOs to restore, separated by commas: ");
+            let input = get_input()?;
+
+            // Validates all numbers before restoring any
+            let mut picked = Vec::new();
+            for part in input.split(',') {
+                let number = part.trim().parse::<usize>()?;
+                if number == 0 || number > restorable.len() {
+                    return Err(SelectionError(part.trim().to_string()).into());
+                }
+                picked.push(restorable[number - 1].clone());
+            }
+
+            let mut todos_ref = todos.borrow_mut();
+            for todo in picked {
+                restore_todo(&mut todos_ref, todo);
+            }
+            println!("Successfully restored the selected TODO(s).");
+        }
+        "" => return Ok(()),
+        _ => {
+            return Err(SelectionError("Invalid Selection".to_string()).into());
+        }
+    }
+
+    action_sleep();
+    Ok(())
+}
+
+/// Shows how the TODOs changed since a snapshot and restores it or some of its
+/// TODOs, unless opened `read_only`.
+pub fn browse_snapshots(
+    store: &SnapshotStore,
+    todos: Todos,
+    templates: &Templates,
+    read_only: bool,
+) -> Result<(), ApplicationError> {
+    browse_snapshots_internal(store, todos, templates, read_only, get_input)
+}
+
+fn import_todos_internal<F>(
+    todos: Todos,
+    template: &Template,
+    mut get_input: F,
+) -> Result<(), ApplicationError>
+where
+    F: FnMut() -> Result<String, std::io::Error>,
+{
+    print_input_label("Enter file to import: ");
+    let input = get_input()?;
+    let path = std::path::Path::new(&input);
+
+    let content = std::fs::read_to_string(path)?;
+    let imported = match Format::from_path(path)? {
+        Format::Csv => match parse_csv(&content, template, &mut get_input)? {
+            Some(imported) => imported,
+            None => return Ok(()),
+        },
+        format => {
+            let parsed = format.parse(&content)?;
+            print_failed_rows(&parsed.failed);
+            parsed
+        }
+    };
+    let result = formats::merge_imported(&mut todos.borrow_mut(), imported.todos, &imported.fields);
+
+    println!(
+        "Successfully imported {}: {} new, {} updated, {} already existing TODO(s).",
+        input, result.added, result.updated, result.duplicates
+    );
+    action_sleep();
+    Ok(())
+}
+
+/// Asks which field every column goes into, then shows what would be imported.
+/// `None` if the import was cancelled.
+fn parse_csv<F>(
+    content: &str,
+    template: &Template,
+    get_input: &mut F,
+) -> Result<Option<csv::Parsed>, ApplicationError>
+where
+    F: FnMut() -> Result<String, std::io::Error>,
+{
+    let header = csv::header(content)?;
+    let mut mapping = csv::guess_mapping(&header);
+    let names: Vec<&str> = Field::ALL.iter().map(|field| field.name()).collect();
+
+    loop {
+        println!("Columns of the file:");
+        for (i, (column, field)) in header.iter().zip(&mapping).enumerate() {
+            let field = field.map_or("-", |field| field.name());
+            println!("# {}: {} -> {}", i + 1, column, field);
+        }
+        print_input_label("Enter number of the column to change or press enter to continue: ");
+        let input = get_input()?;
+        if input.is_empty() {
+            break;
+        }
+        let Some(i) = input
+            .parse::<usize>()
+            .ok()
+            .filter(|i| (1..=header.len()).contains(i))
+        else {
+            println!("{}", SelectionError(format!("No column # {}", input)));
+            continue;
+        };
+        print_input_label(&format!(
+            "Enter field ({}) or press enter to skip the column: ",
+            names.join(", ")
+        ));
+        let field = get_input()?;
+        if field.is_empty() {
+            mapping[i - 1] = None;
+        } else {
+            match Field::from_name(&field) {
+                Some(field) => mapping[i - 1] = Some(field),
+                None => println!("{}", SelectionError(format!("No field {}", field))),
+            }
+        }
+    }
+
+    let parsed = csv::parse(content, &mapping)?;
+    println!("Preview:");
+    for todo in parsed.todos.iter().take(5) {
+        println!("{}", template.render(todo));
+    }
+    if parsed.todos.len() > 5 {
+        println!("... and {} more", parsed.todos.len() - 5);
+    }
+    print_failed_rows(&parsed.failed);
+    print_input_label(&format!("Import {} TODO(s)? [y/N]: ", parsed.todos.len()));
+    let input = get_input()?;
+    Ok(input.eq_ignore_ascii_case("y").then_some(parsed))
+}
+
+fn print_failed_rows(failed: &[(u64, String)]) {
+    if !failed.is_empty() {
+        println!("{} row(s) failed to parse and are skipped:", failed.len());
+        for (line, err) in failed {
+            println!("Line {}: {}", line, err);
+        }
+    }
+}
+
+pub fn import_todos(todos: Todos, template: &Template) -> Result<(), ApplicationError> {
+    import_todos_internal(todos, template, get_input)
+}
+
+fn export_todos_internal<F>(
+    todos: Todos,
+    csv_config: &CsvConfig,
+    templates: &Templates,
+    mut get_input: F,
+) -> Result<(), ApplicationError>
+where
+    F: FnMut() -> Result<String, std::io::Error>,
+{
+    print_input_label("Enter file to export to: ");
+    let input = get_input()?;
+    let path = std::path::Path::new(&input);
+
+    // A named format writes a line per TODO, whatever the file is called
+    let mut template = None;
+    if !templates.formats.is_empty() {
+        let names: Vec<&str> = templates.formats.keys().map(String::as_str).collect();
+        print_input_label(&format!(
+            "Enter format ({}) or press enter to use the file's format: ",
+            names.join(", ")
+        ));
+        let name = get_input()?;
+        if !name.is_empty() {
+            template = Some(templates.formats.get(&name).ok_or(SelectionError(name))?);
+        }
+    }
+
+    let content = match template {
+        Some(template) => todos
+            .borrow()
+            .iter()
+            .map(|todo| template.render(todo) + "\n")
+            .collect(),
+        None => match Format::from_path(path)? {
+            Format::Csv => csv::write(&todos.borrow(), &csv_config.columns)?,
+            format => format.write(&todos.borrow())?,
+        },
+    };
+    std::fs::write(path, content)?;
+
+    println!(
+        "Successfully exported {} TODO(s) to {}.",
+        todos.borrow().len(),
+        input
+    );
+    action_sleep();
+    Ok(())
 }
 
-pub fn edit_todo(todos: Todos) -> Result<(), ApplicationError> {
-    edit_todo_internal(todos, get_input)
+pub fn export_todos(
+    todos: Todos,
+    csv_config: &CsvConfig,
+    templates: &Templates,
+) -> Result<(), ApplicationError> {
+    export_todos_internal(todos, csv_config, templates, get_input)
+}
+
+fn scan_source_internal<F>(todos: Todos, mut get_input: F) -> Result<(), ApplicationError>
+where
+    F: FnMut() -> Result<String, std::io::Error>,
+{
+    print_input_label("Enter directory to scan for TODO comments: ");
+    let input = get_input()?;
+    let root = std::path::Path::new(&input);
+
+    let markers = scan::scan(root)?;
+    let today = chrono::Local::now().date_naive();
+    let result = scan::apply(&mut todos.borrow_mut(), root, &markers, today);
+
+    println!(
+        "Found {} TODO comment(s) in {}: {} new, {} moved, {} closed as their comment is gone.",
+        markers.len(),
+        input,
+        result.added,
+        result.moved,
+        result.closed
+    );
+    action_sleep();
+    Ok(())
+}
+
+pub fn scan_source(todos: Todos) -> Result<(), ApplicationError> {
+    scan_source_internal(todos, get_input)
 }
 
 #[cfg(test)]
 mod tests {
     use std::rc::Rc;
-    use std::{cell::RefCell, sync::Arc};
+    use std::cell::RefCell;
     use super::*;
 
     struct MockInputProvider {
@@ -285,20 +792,37 @@ mod tests {
 
     #[test]
     fn test_list_todos() {
-        let mock_inputs = vec![GetInputVal::new(GetInputValType::String, "".to_string()), GetInputVal::new(GetInputValType::Error, "".to_string())];
+        let mock_inputs = vec![
+            GetInputVal::new(GetInputValType::String, "".to_string()),
+            GetInputVal::new(GetInputValType::String, "".to_string()),
+            GetInputVal::new(
+                GetInputValType::String,
+                "status:open text~\"^s\"".to_string(),
+            ),
+            GetInputVal::new(GetInputValType::String, "".to_string()),
+            GetInputVal::new(GetInputValType::String, "status:".to_string()),
+            GetInputVal::new(GetInputValType::Error, "".to_string()),
+        ];
         let provider = MockInputProvider::new(mock_inputs);
 
-        let todos: Todos = Arc::new(RefCell::new(vec![
+        let todos: Todos = Rc::new(RefCell::new(vec![
             Todo::new("first".to_string()),
             Todo::new("second".to_string()),
             Todo::new("third".to_string()),
         ]));
 
-        let res = list_todos_internal(todos.clone(), provider.get_fn());
+        let res = list_todos_internal(todos.clone(), &Template::default(), provider.get_fn());
         assert!(res.is_ok());
         assert_eq!(todos.borrow().len(), 3);
 
-        let res = list_todos_internal(todos.clone(), provider.get_fn());
+        let res = list_todos_internal(todos.clone(), &Template::default(), provider.get_fn());
+        assert!(res.is_ok());
+
+        // An invalid filter
+        let res = list_todos_internal(todos.clone(), &Template::default(), provider.get_fn());
+        assert!(res.is_err());
+
+        let res = list_todos_internal(todos.clone(), &Template::default(), provider.get_fn());
         assert!(res.is_err());
         assert_eq!(todos.borrow().len(), 3);
     }
@@ -312,7 +836,7 @@ mod tests {
         ];
         let provider = MockInputProvider::new(mock_inputs);
 
-        let todos: Todos = Arc::new(RefCell::new(vec![
+        let todos: Todos = Rc::new(RefCell::new(vec![
             Todo::new("first".to_string()),
             Todo::new("second".to_string()),
             Todo::new("third".to_string()),
@@ -320,13 +844,13 @@ mod tests {
 
         let res = create_todo_internal(todos.clone(), provider.get_fn());
         assert_eq!(todos.borrow().len(), 4);
-        assert_eq!(todos.borrow().get(3).unwrap().completed, false);
+        assert!(!todos.borrow().get(3).unwrap().completed);
         assert_eq!(todos.borrow().get(3).unwrap().text, "Foo");
         assert!(res.is_ok());
 
         let res = create_todo_internal(todos.clone(), provider.get_fn());
         assert_eq!(todos.borrow().len(), 5);
-        assert_eq!(todos.borrow().get(4).unwrap().completed, false);
+        assert!(!todos.borrow().get(4).unwrap().completed);
         assert_eq!(todos.borrow().get(4).unwrap().text, "Bar");
         assert!(res.is_ok());
 
@@ -345,33 +869,35 @@ mod tests {
         ];
         let provider = MockInputProvider::new(mock_inputs);
 
-        let todos: Todos = Arc::new(RefCell::new(vec![
+        let todos: Todos = Rc::new(RefCell::new(vec![
             Todo::new("first".to_string()),
             Todo::new("second".to_string()),
             Todo::new("third".to_string()),
         ]));
 
-        let res = complete_todo_internal(todos.clone(), provider.get_fn());
+        let res = complete_todo_internal(todos.clone(), &Template::default(), provider.get_fn());
         assert_eq!(todos.borrow().len(), 3);
-        assert_eq!(todos.borrow().get(0).unwrap().completed, true);
-        assert_eq!(todos.borrow().get(0).unwrap().text, "first");
+        assert!(todos.borrow().first().unwrap().completed);
+        assert_eq!(todos.borrow().first().unwrap().text, "first");
+        let today = chrono::Local::now().date_naive();
+        assert_eq!(todos.borrow().first().unwrap().completed_at, Some(today));
         assert!(res.is_ok());
 
-        let res = complete_todo_internal(todos.clone(), provider.get_fn());
+        let res = complete_todo_internal(todos.clone(), &Template::default(), provider.get_fn());
         assert_eq!(todos.borrow().len(), 3);
-        assert_eq!(todos.borrow().get(2).unwrap().completed, true);
+        assert!(todos.borrow().get(2).unwrap().completed);
         assert_eq!(todos.borrow().get(2).unwrap().text, "third");
         assert!(res.is_ok());
 
-        let res = complete_todo_internal(todos.clone(), provider.get_fn());
+        let res = complete_todo_internal(todos.clone(), &Template::default(), provider.get_fn());
         assert_eq!(todos.borrow().len(), 3);
-        assert_eq!(todos.borrow().get(2).unwrap().completed, true);
+        assert!(todos.borrow().get(2).unwrap().completed);
         assert_eq!(todos.borrow().get(2).unwrap().text, "third");
         assert!(res.is_err()); // Input Error
 
-        let res = complete_todo_internal(todos.clone(), provider.get_fn());
+        let res = complete_todo_internal(todos.clone(), &Template::default(), provider.get_fn());
         assert_eq!(todos.borrow().len(), 3);
-        assert_eq!(todos.borrow().get(2).unwrap().completed, true);
+        assert!(todos.borrow().get(2).unwrap().completed);
         assert_eq!(todos.borrow().get(2).unwrap().text, "third");
         assert!(res.is_err()); // Selection Error
     }
@@ -386,15 +912,18 @@ mod tests {
         ];
         let provider = MockInputProvider::new(mock_inputs);
 
-        let todos: Todos = Arc::new(RefCell::new(vec![
+        let todos: Todos = Rc::new(RefCell::new(vec![
             Todo::new("first".to_string()),
             Todo::new("second".to_string()),
             Todo::new("third".to_string()),
         ]));
+        let first_id = todos.borrow()[0].id;
+        todos.borrow_mut()[1].parent = Some(first_id);
 
-        let res = delete_todo_internal(todos.clone(), provider.get_fn());
+        let res = delete_todo_internal(todos.clone(), &Template::default(), provider.get_fn());
         assert!(res.is_ok());
         assert_eq!(todos.borrow().len(), 2);
+        assert!(todos.borrow().iter().all(|todo| todo.parent.is_none()));
         assert!(todos
             .borrow()
             .iter()
@@ -404,20 +933,20 @@ mod tests {
             .iter()
             .any(|todo: &Todo| todo.text.eq("third")));
 
-        let res = delete_todo_internal(todos.clone(), provider.get_fn());
+        let res = delete_todo_internal(todos.clone(), &Template::default(), provider.get_fn());
         assert!(res.is_ok());
         assert_eq!(todos.borrow().len(), 1);
-        assert_eq!(todos.borrow().get(0).unwrap().text, "third");
+        assert_eq!(todos.borrow().first().unwrap().text, "third");
 
-        let res = delete_todo_internal(todos.clone(), provider.get_fn());
+        let res = delete_todo_internal(todos.clone(), &Template::default(), provider.get_fn());
         assert!(res.is_err()); // Input Error
         assert_eq!(todos.borrow().len(), 1);
-        assert_eq!(todos.borrow().get(0).unwrap().text, "third");
+        assert_eq!(todos.borrow().first().unwrap().text, "third");
 
-        let res = delete_todo_internal(todos.clone(), provider.get_fn());
+        let res = delete_todo_internal(todos.clone(), &Template::default(), provider.get_fn());
         assert!(res.is_err()); // Selection Error
         assert_eq!(todos.borrow().len(), 1);
-        assert_eq!(todos.borrow().get(0).unwrap().text, "third");
+        assert_eq!(todos.borrow().first().unwrap().text, "third");
     }
 
     #[test]
@@ -440,52 +969,403 @@ mod tests {
         ];
         let provider = MockInputProvider::new(mock_inputs);
 
-        let todos: Todos = Arc::new(RefCell::new(vec![
+        let todos: Todos = Rc::new(RefCell::new(vec![
             Todo::new("first".to_string()),
             Todo::new("second".to_string()),
             Todo::new("third".to_string()),
         ]));
 
-        let res = edit_todo_internal(todos.clone(), provider.get_fn());
+        let res = edit_todo_internal(todos.clone(), &Template::default(), provider.get_fn());
         assert!(res.is_ok());
         assert_eq!(todos.borrow().len(), 3);
-        assert_eq!(todos.borrow().get(0).unwrap().completed, false);
-        assert_eq!(todos.borrow().get(0).unwrap().text, "first edited");
+        assert!(!todos.borrow().first().unwrap().completed);
+        assert_eq!(todos.borrow().first().unwrap().text, "first edited");
 
-        let res = edit_todo_internal(todos.clone(), provider.get_fn());
+        let res = edit_todo_internal(todos.clone(), &Template::default(), provider.get_fn());
         assert!(res.is_ok());
         assert_eq!(todos.borrow().len(), 3);
-        assert_eq!(todos.borrow().get(0).unwrap().completed, false);
-        assert_eq!(todos.borrow().get(0).unwrap().text, "first edited again");
+        assert!(!todos.borrow().first().unwrap().completed);
+        assert_eq!(todos.borrow().first().unwrap().text, "first edited again");
 
-        let res = edit_todo_internal(todos.clone(), provider.get_fn());
+        let res = edit_todo_internal(todos.clone(), &Template::default(), provider.get_fn());
         assert!(res.is_ok());
         assert_eq!(todos.borrow().len(), 3);
-        assert_eq!(todos.borrow().get(0).unwrap().completed, true);
-        assert_eq!(todos.borrow().get(0).unwrap().text, "first edited again");
+        assert!(todos.borrow().first().unwrap().completed);
+        assert_eq!(todos.borrow().first().unwrap().text, "first edited again");
+        let today = chrono::Local::now().date_naive();
+        assert_eq!(todos.borrow().first().unwrap().completed_at, Some(today));
 
-        let res = edit_todo_internal(todos.clone(), provider.get_fn());
+        let res = edit_todo_internal(todos.clone(), &Template::default(), provider.get_fn());
         assert!(res.is_ok());
         assert_eq!(todos.borrow().len(), 3);
-        assert_eq!(todos.borrow().get(0).unwrap().completed, false);
-        assert_eq!(todos.borrow().get(0).unwrap().text, "first edited again");
+        assert!(!todos.borrow().first().unwrap().completed);
+        assert_eq!(todos.borrow().first().unwrap().text, "first edited again");
+        assert_eq!(todos.borrow().first().unwrap().completed_at, None);
 
-        let res = edit_todo_internal(todos.clone(), provider.get_fn());
+        let res = edit_todo_internal(todos.clone(), &Template::default(), provider.get_fn());
         assert!(res.is_err()); // Input Error
         assert_eq!(todos.borrow().len(), 3);
-        assert_eq!(todos.borrow().get(0).unwrap().completed, false);
-        assert_eq!(todos.borrow().get(0).unwrap().text, "first edited again");
+        assert!(!todos.borrow().first().unwrap().completed);
+        assert_eq!(todos.borrow().first().unwrap().text, "first edited again");
 
-        let res = edit_todo_internal(todos.clone(), provider.get_fn());
+        let res = edit_todo_internal(todos.clone(), &Template::default(), provider.get_fn());
         assert!(res.is_err()); // Selection Error
         assert_eq!(todos.borrow().len(), 3);
-        assert_eq!(todos.borrow().get(0).unwrap().completed, false);
-        assert_eq!(todos.borrow().get(0).unwrap().text, "first edited again");
+        assert!(!todos.borrow().first().unwrap().completed);
+        assert_eq!(todos.borrow().first().unwrap().text, "first edited again");
 
-        let res = edit_todo_internal(todos.clone(), provider.get_fn());
+        let res = edit_todo_internal(todos.clone(), &Template::default(), provider.get_fn());
         assert!(res.is_err()); // Selection Error
         assert_eq!(todos.borrow().len(), 3);
-        assert_eq!(todos.borrow().get(0).unwrap().completed, false);
-        assert_eq!(todos.borrow().get(0).unwrap().text, "first edited again");
+        assert!(!todos.borrow().first().unwrap().completed);
+        assert_eq!(todos.borrow().first().unwrap().text, "first edited again");
+    }
+
+    #[test]
+    fn test_sync_file() {
+        let path = std::env::temp_dir().join(format!("todo_sync_file_{}.json", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let ours: Todos = Rc::new(RefCell::new(vec![Todo::new("ours".to_string())]));
+        let mut replica = Replica::new();
+        let provider = MockInputProvider::new(vec![GetInputVal::new(
+            GetInputValType::String,
+            path.display().to_string(),
+        )]);
+        let res = sync_file_internal(&mut replica, ours.clone(), provider.get_fn());
+        assert!(res.is_ok());
+        assert!(path.exists());
+
+        let theirs: Todos = Rc::new(RefCell::new(vec![Todo::new("theirs".to_string())]));
+        let mut other = Replica::new();
+        let provider = MockInputProvider::new(vec![
+            GetInputVal::new(GetInputValType::String, path.display().to_string()),
+            GetInputVal::new(GetInputValType::Error, "".to_string()),
+        ]);
+        let res = sync_file_internal(&mut other, theirs.clone(), provider.get_fn());
+        assert!(res.is_ok());
+        assert_eq!(theirs.borrow().len(), 2);
+        assert_eq!(theirs.borrow()[0].text, "theirs");
+        assert_eq!(theirs.borrow()[1].text, "ours");
+
+        let res = sync_file_internal(&mut other, theirs.clone(), provider.get_fn());
+        assert!(res.is_err()); // Input Error
+        assert_eq!(theirs.borrow().len(), 2);
+
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn test_action_numbers() {
+        // The numbers must stay the same, people know them by heart
+        assert!(matches!(Action::from("1\n".to_string()), Action::Create));
+        assert!(matches!(Action::from("6\r\n".to_string()), Action::Exit));
+        assert!(matches!(
+            Action::from("7".to_string()),
+            Action::ChangePassphrase
+        ));
+        assert!(matches!(Action::from("14".to_string()), Action::Scan));
+        assert!(matches!(Action::from("15".to_string()), Action::Invalid));
+    }
+
+    #[test]
+    fn test_sync_file_log_storage() {
+        use crate::storage::{self, tests::temp_path, EventLogStorage, Storage};
+
+        let dir = temp_path("sync_file_log");
+        let path = dir.join("replica.json");
+        std::fs::create_dir_all(&dir).unwrap();
+        let theirs: Todos = Rc::new(RefCell::new(vec![Todo::new("theirs".to_string())]));
+        let provider = MockInputProvider::new(vec![GetInputVal::new(
+            GetInputValType::String,
+            path.display().to_string(),
+        )]);
+        sync_file_internal(&mut Replica::new(), theirs, provider.get_fn()).unwrap();
+
+        let mut log = EventLogStorage::new(dir.join("log"), 100);
+        let ours: Todos = Rc::new(RefCell::new(vec![Todo::new("ours".to_string())]));
+        log.save(&ours.borrow()).unwrap();
+        let before = ours.borrow().clone();
+        let provider = MockInputProvider::new(vec![GetInputVal::new(
+            GetInputValType::String,
+            path.display().to_string(),
+        )]);
+        sync_file_internal(&mut Replica::new(), ours.clone(), provider.get_fn()).unwrap();
+        let changes = storage::diff(&before, &ours.borrow());
+        log.record(&changes).unwrap();
+        log.save(&ours.borrow()).unwrap();
+        // Replaying an insert twice, as recording a sync again did, keeps one TODO
+        log.record(&changes).unwrap();
+
+        let loaded = EventLogStorage::new(dir.join("log"), 100).load().unwrap();
+        assert_eq!(loaded, *ours.borrow());
+        assert_eq!(loaded.len(), 2);
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_snapshots() {
+        let dir =
+            std::env::temp_dir().join(format!("todo_snapshot_actions_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let store = SnapshotStore::new(&dir);
+
+        let todos: Todos = Rc::new(RefCell::new(vec![
+            Todo::new("first".to_string()),
+            Todo::new("second".to_string()),
+            Todo::new("third".to_string()),
+        ]));
+        let original = todos.borrow().clone();
+        let mock_inputs = vec![
+            GetInputVal::new(GetInputValType::String, "label".to_string()),
+            GetInputVal::new(GetInputValType::String, "1".to_string()),
+            GetInputVal::new(GetInputValType::String, "C".to_string()),
+            GetInputVal::new(GetInputValType::String, "1, 2".to_string()),
+            GetInputVal::new(GetInputValType::String, "2".to_string()),
+            GetInputVal::new(GetInputValType::String, "1".to_string()),
+            GetInputVal::new(GetInputValType::String, "r".to_string()),
+        ];
+        let provider = MockInputProvider::new(mock_inputs);
+
+        let res = create_snapshot_internal(&store, todos.clone(), provider.get_fn());
+        assert!(res.is_ok());
+
+        todos.borrow_mut().swap_remove(0);
+        todos.borrow_mut()[0].completed = true;
+        todos.borrow_mut().push(Todo::new("fourth".to_string()));
+
+        // Restorable are the deleted first, then the completed third
+        let res = browse_snapshots_internal(
+            &store,
+            todos.clone(),
+            &Templates::default(),
+            false,
+            provider.get_fn(),
+        );
+        assert!(res.is_ok());
+        assert_eq!(todos.borrow().len(), 4);
+        assert_eq!(todos.borrow()[0], original[2]);
+        assert_eq!(todos.borrow()[3], original[0]);
+
+        let res = browse_snapshots_internal(
+            &store,
+            todos.clone(),
+            &Templates::default(),
+            false,
+            provider.get_fn(),
+        );
+        assert!(res.is_err()); // Selection Error
+        let res = browse_snapshots_internal(
+            &store,
+            todos.clone(),
+            &Templates::default(),
+            false,
+            provider.get_fn(),
+        );
+        assert!(res.is_ok());
+        assert_eq!(*todos.borrow(), original);
+
+        // Read-only, the changes are only shown
+        todos.borrow_mut().clear();
+        let provider = MockInputProvider::new(vec![
+            GetInputVal::new(GetInputValType::String, "1".to_string()),
+            GetInputVal::new(GetInputValType::String, "r".to_string()),
+        ]);
+        let res = browse_snapshots_internal(
+            &store,
+            todos.clone(),
+            &Templates::default(),
+            true,
+            provider.get_fn(),
+        );
+        assert!(res.is_ok());
+        assert!(todos.borrow().is_empty());
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_import_export() {
+        let path = std::env::temp_dir().join(format!("todo_export_{}.txt", std::process::id()));
+        let path_input = path.display().to_string();
+        let mock_inputs = vec![
+            GetInputVal::new(GetInputValType::String, path_input.clone()),
+            GetInputVal::new(GetInputValType::String, path_input.clone()),
+            GetInputVal::new(GetInputValType::String, path_input),
+            GetInputVal::new(GetInputValType::String, "todos.unknown".to_string()),
+        ];
+        let provider = MockInputProvider::new(mock_inputs);
+
+        let todos: Todos = Rc::new(RefCell::new(vec![
+            Todo::new("first".to_string()),
+            Todo::new("second".to_string()),
+        ]));
+        let res = export_todos_internal(
+            todos.clone(),
+            &CsvConfig::default(),
+            &Templates::default(),
+            provider.get_fn(),
+        );
+        assert!(res.is_ok());
+
+        // Importing the export again changes nothing, into another list adds all
+        let res = import_todos_internal(todos.clone(), &Template::default(), provider.get_fn());
+        assert!(res.is_ok());
+        assert_eq!(todos.borrow().len(), 2);
+        let others: Todos = Rc::new(RefCell::new(vec![Todo::new("other".to_string())]));
+        let res = import_todos_internal(others.clone(), &Template::default(), provider.get_fn());
+        assert!(res.is_ok());
+        assert_eq!(others.borrow().len(), 3);
+        assert_eq!(others.borrow()[1..], todos.borrow()[..]);
+
+        let res = import_todos_internal(todos.clone(), &Template::default(), provider.get_fn());
+        assert!(res.is_err()); // Unknown format
+
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn test_export_with_format() {
+        let path = std::env::temp_dir().join(format!("todo_format_{}.txt", std::process::id()));
+        let path_input = path.display().to_string();
+        let mock_inputs = vec![
+            GetInputVal::new(GetInputValType::String, path_input.clone()),
+            GetInputVal::new(GetInputValType::String, "long".to_string()),
+            GetInputVal::new(GetInputValType::String, path_input),
+            GetInputVal::new(GetInputValType::String, "short".to_string()),
+        ];
+        let provider = MockInputProvider::new(mock_inputs);
+        let config = crate::template::TemplateConfig {
+            formats: [("short".to_string(), "[{check}] {text}".to_string())].into(),
+            ..Default::default()
+        };
+        let templates = config.templates().unwrap();
+
+        let mut completed = Todo::new("second".to_string());
+        completed.completed = true;
+        let todos: Todos = Rc::new(RefCell::new(vec![
+            Todo::new("first".to_string()),
+            completed,
+        ]));
+        let res = export_todos_internal(
+            todos.clone(),
+            &CsvConfig::default(),
+            &templates,
+            provider.get_fn(),
+        );
+        assert!(res.is_err()); // Unknown format
+        let res = export_todos_internal(
+            todos.clone(),
+            &CsvConfig::default(),
+            &templates,
+            provider.get_fn(),
+        );
+        assert!(res.is_ok());
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "[ ] first\n[x] second\n"
+        );
+
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn test_import_csv() {
+        let path = std::env::temp_dir().join(format!("todo_import_{}.csv", std::process::id()));
+        std::fs::write(
+            &path,
+            "Task,Done,Note
+first,yes,a
+second,maybe,b
+third,,c
+",
+        )
+        .unwrap();
+        let path_input = path.display().to_string();
+        let mock_inputs = vec![
+            GetInputVal::new(GetInputValType::String, path_input.clone()),
+            GetInputVal::new(GetInputValType::String, "".to_string()), // No text column
+            GetInputVal::new(GetInputValType::String, path_input.clone()),
+            GetInputVal::new(GetInputValType::String, "1".to_string()),
+            GetInputVal::new(GetInputValType::String, "text".to_string()),
+            GetInputVal::new(GetInputValType::String, "4".to_string()), // Invalid column
+            GetInputVal::new(GetInputValType::String, "2".to_string()),
+            GetInputVal::new(GetInputValType::String, "Completed".to_string()),
+            GetInputVal::new(GetInputValType::String, "".to_string()),
+            GetInputVal::new(GetInputValType::String, "n".to_string()), // Cancelled
+            GetInputVal::new(GetInputValType::String, path_input),
+            GetInputVal::new(GetInputValType::String, "1".to_string()),
+            GetInputVal::new(GetInputValType::String, "text".to_string()),
+            GetInputVal::new(GetInputValType::String, "2".to_string()),
+            GetInputVal::new(GetInputValType::String, "completed".to_string()),
+            GetInputVal::new(GetInputValType::String, "".to_string()),
+            GetInputVal::new(GetInputValType::String, "y".to_string()),
+        ];
+        let provider = MockInputProvider::new(mock_inputs);
+
+        let todos: Todos = Rc::new(RefCell::new(vec![]));
+        let res = import_todos_internal(todos.clone(), &Template::default(), provider.get_fn());
+        assert!(res.is_err());
+        let res = import_todos_internal(todos.clone(), &Template::default(), provider.get_fn());
+        assert!(res.is_ok());
+        assert!(todos.borrow().is_empty());
+
+        // The row that failed to parse is skipped
+        let res = import_todos_internal(todos.clone(), &Template::default(), provider.get_fn());
+        assert!(res.is_ok());
+        let texts: Vec<String> = todos
+            .borrow()
+            .iter()
+            .map(|todo| todo.text.clone())
+            .collect();
+        assert_eq!(texts, vec!["first", "third"]);
+        assert!(todos.borrow()[0].completed);
+        assert!(!todos.borrow()[1].completed);
+
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn test_scan_source() {
+        let dir = std::env::temp_dir().join(format!("todo_scan_action_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join("main.rs"),
+            "// TODO: first\nlet s = \"// TODO: no\";\n",
+        )
+        .unwrap();
+
+        let todos: Todos = Rc::new(RefCell::new(vec![Todo::new("unrelated".to_string())]));
+        let mock_inputs = vec![
+            GetInputVal::new(GetInputValType::String, dir.display().to_string()),
+            GetInputVal::new(GetInputValType::String, dir.join(".").display().to_string()),
+            GetInputVal::new(
+                GetInputValType::String,
+                dir.join("main.rs").display().to_string(),
+            ),
+            GetInputVal::new(GetInputValType::Error, "".to_string()),
+        ];
+        let provider = MockInputProvider::new(mock_inputs);
+
+        let res = scan_source_internal(todos.clone(), provider.get_fn());
+        assert!(res.is_ok());
+        assert_eq!(todos.borrow().len(), 2);
+        assert_eq!(todos.borrow()[1].text, "TODO: first");
+        assert_eq!(todos.borrow()[1].metadata[scan::LINE_KEY], "1");
+
+        // Scanning it again, given another way, finds the same TODO
+        let res = scan_source_internal(todos.clone(), provider.get_fn());
+        assert!(res.is_ok());
+        assert_eq!(todos.borrow().len(), 2);
+
+        let res = scan_source_internal(todos.clone(), provider.get_fn());
+        assert!(res.is_err()); // No directory
+        let res = scan_source_internal(todos.clone(), provider.get_fn());
+        assert!(res.is_err()); // Input Error
+        assert_eq!(todos.borrow().len(), 2);
+
+        let _ = std::fs::remove_dir_all(dir);
     }
 }
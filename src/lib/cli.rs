@@ -0,0 +1,739 @@
+//! Subcommands to use the TODO list from scripts, without the interactive menu.
+
+use crate::{
+    action::{find_todo, remove_todo},
+    doctor::Finding,
+    errors::{ApplicationError, SelectionError},
+    output::{self, OutputFormat},
+    query::Filter,
+    template::Templates,
+    todo::Todo,
+};
+use chrono::Local;
+use std::{
+    fmt,
+    io::{self, Read},
+    path::PathBuf,
+};
+
+pub const USAGE: &str = "Usage:
+  todo_cmd                                    Open the interactive menu
+  todo_cmd add <text>                         Add a TODO
+  todo_cmd list [<filter>]                    List the TODOs, or those matching
+  todo_cmd done <id>                          Complete a TODO
+  todo_cmd edit <id> [--text <text>] [--completed <true|false>]
+                                              Change a TODO
+  todo_cmd rm <id>                            Delete a TODO
+  todo_cmd tag <id> <+tag|@tag>...            Add tags to a TODO
+  todo_cmd batch [<file>]                     Run the commands in a file or read
+                                              from stdin, one per line
+  todo_cmd doctor [--dry-run]                 Check the data and repair it
+<id> is the number a TODO is listed with, or its id or at least 4 characters of it.
+done, edit, rm and tag change all TODOs matching a filter with --where <filter>
+instead of <id>, e.g. done --where \"status:open and due<today\".
+Options:
+  --format <text|json|jsonl>                  How to print the results";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    Add {
+        text: String,
+    },
+    List {
+        filter: Option<Filter>,
+    },
+    Done {
+        id: String,
+    },
+    Edit {
+        id: String,
+        text: Option<String>,
+        completed: Option<bool>,
+    },
+    Rm {
+        id: String,
+    },
+    Tag {
+        id: String,
+        tags: Vec<String>,
+    },
+    /// Runs the command on every TODO matching the filter, with the TODO's id
+    Bulk {
+        filter: Filter,
+        command: Box<Command>,
+    },
+    /// Runs a script, stdin if there is no path
+    Batch {
+        path: Option<PathBuf>,
+    },
+    Doctor {
+        dry_run: bool,
+    },
+}
+
+/// What a command did, to report it. TODOs come with the number they are
+/// selected with.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Outcome {
+    List(Vec<(usize, Todo)>),
+    Added(usize, Todo),
+    Completed(usize, Todo),
+    Edited(usize, Todo),
+    Removed(usize, Todo),
+    Tagged(usize, Todo),
+    /// The outcomes of the commands of a script
+    Batch(Vec<Outcome>),
+    Doctor {
+        findings: Vec<Finding>,
+        /// Whether all findings can be repaired automatically
+        repairable: bool,
+        dry_run: bool,
+        /// Where the data was backed up before it was repaired
+        backup: Option<PathBuf>,
+        /// Whether the stored data was checked as it is, not only the loaded TODOs
+        content_checked: bool,
+    },
+}
+
+/// Why a command failed, with a stable code for machine-readable output.
+#[derive(Debug, Clone)]
+pub enum Error {
+    /// The arguments are invalid
+    Usage(String),
+    Selection(SelectionError),
+    Application(ApplicationError),
+    /// Another program changed the same TODOs meanwhile, nothing was stored
+    Conflict(String),
+}
+
+impl Error {
+    pub fn code(&self) -> &'static str {
+        match self {
+            Error::Usage(_) => "invalid_arguments",
+            Error::Selection(_) => "invalid_selection",
+            Error::Application(_) => "application_error",
+            Error::Conflict(_) => "conflict",
+        }
+    }
+
+    /// The error of a line of a script.
+    fn at_line(self, line: usize) -> Error {
+        let at_line = |message: String| format!("Line {}: {}", line, message);
+        match self {
+            Error::Usage(message) => Error::Usage(at_line(message)),
+            Error::Selection(SelectionError(message)) => {
+                Error::Selection(SelectionError(at_line(message)))
+            }
+            Error::Application(ApplicationError(message)) => {
+                Error::Application(ApplicationError(at_line(message)))
+            }
+            Error::Conflict(message) => Error::Conflict(at_line(message)),
+        }
+    }
+
+    pub fn message(&self) -> String {
+        match self {
+            Error::Usage(message) => message.clone(),
+            Error::Selection(SelectionError(message)) => message.clone(),
+            Error::Application(ApplicationError(message)) => message.clone(),
+            Error::Conflict(message) => message.clone(),
+        }
+    }
+
+    /// The error as printed in the format.
+    pub fn render(&self, format: OutputFormat) -> String {
+        match format {
+            OutputFormat::Text => format!("{}\n", self),
+            format => output::error(self, format),
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Usage(reason) => write!(f, "{}\n{}", reason, USAGE),
+            Error::Selection(err) => write!(f, "{}", ApplicationError::from(err.clone())),
+            Error::Application(err) => write!(f, "{}", err),
+            Error::Conflict(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl From<SelectionError> for Error {
+    fn from(val: SelectionError) -> Self {
+        Error::Selection(val)
+    }
+}
+
+impl From<ApplicationError> for Error {
+    fn from(val: ApplicationError) -> Self {
+        Error::Application(val)
+    }
+}
+
+/// Takes the `--format` option out of the arguments.
+pub fn take_format(args: &mut Vec<String>) -> Result<OutputFormat, Error> {
+    let Some(i) = args.iter().position(|arg| arg == "--format") else {
+        return Ok(OutputFormat::Text);
+    };
+    let name = args
+        .get(i + 1)
+        .ok_or_else(|| Error::Usage("--format needs a value".to_string()))?;
+    let format = OutputFormat::from_name(name)
+        .ok_or_else(|| Error::Usage(format!("Unknown format {}", name)))?;
+    args.drain(i..i + 2);
+    Ok(format)
+}
+
+/// Parses the arguments after the program name, `None` if there are none and the
+/// menu should be shown.
+pub fn parse(args: &[String]) -> Result<Option<Command>, Error> {
+    let invalid = |reason: &str| Error::Usage(reason.to_string());
+    let Some((name, rest)) = args.split_first() else {
+        return Ok(None);
+    };
+    let rest: Vec<&str> = rest.iter().map(String::as_str).collect();
+    let filter = |source: &str| {
+        Filter::parse(source).map_err(|ApplicationError(message)| Error::Usage(message))
+    };
+
+    if let ("done" | "edit" | "rm" | "tag", ["--where", source, rest @ ..]) =
+        (name.as_str(), rest.as_slice())
+    {
+        let filter = filter(source)?;
+        let args: Vec<String> = [name.as_str(), ""]
+            .iter()
+            .chain(rest)
+            .map(|arg| arg.to_string())
+            .collect();
+        let command = parse(&args)?.expect("the arguments aren't empty");
+        return Ok(Some(Command::Bulk {
+            filter,
+            command: Box::new(command),
+        }));
+    }
+
+    let command = match (name.as_str(), rest.as_slice()) {
+        ("add", [text]) => Command::Add {
+            text: text.to_string(),
+        },
+        ("list", []) => Command::List { filter: None },
+        ("list", [source]) => Command::List {
+            filter: Some(filter(source)?),
+        },
+        ("done", [id]) => Command::Done { id: id.to_string() },
+        ("edit", [id, options @ ..]) => {
+            let (mut text, mut completed) = (None, None);
+            let mut options = options.iter();
+            while let Some(option) = options.next() {
+                let value = options
+                    .next()
+                    .ok_or_else(|| invalid(&format!("{} needs a value", option)))?;
+                match *option {
+                    "--text" => text = Some(value.to_string()),
+                    "--completed" => {
+                        completed = Some(value.parse().map_err(|_| {
+                            invalid(&format!("--completed is true or false, not {}", value))
+                        })?)
+                    }
+                    option => return Err(invalid(&format!("Unknown option {}", option))),
+                }
+            }
+            if text.is_none() && completed.is_none() {
+                return Err(invalid("Nothing to edit"));
+            }
+            Command::Edit {
+                id: id.to_string(),
+                text,
+                completed,
+            }
+        }
+        ("rm", [id]) => Command::Rm { id: id.to_string() },
+        ("tag", [id, tags @ ..]) if !tags.is_empty() => {
+            if let Some(tag) = tags.iter().find(|tag| !is_tag(tag)) {
+                return Err(invalid(&format!("{} is no +tag or @tag", tag)));
+            }
+            Command::Tag {
+                id: id.to_string(),
+                tags: tags.iter().map(|tag| tag.to_string()).collect(),
+            }
+        }
+        ("batch", []) | ("batch", ["-"]) => Command::Batch { path: None },
+        ("batch", [path]) => Command::Batch {
+            path: Some(PathBuf::from(path)),
+        },
+        ("doctor", []) => Command::Doctor { dry_run: false },
+        ("doctor", ["--dry-run"]) => Command::Doctor { dry_run: true },
+        ("add" | "list" | "done" | "edit" | "rm" | "tag" | "batch" | "doctor", _) => {
+            return Err(invalid(&format!("Invalid arguments for {}", name)))
+        }
+        (name, _) => return Err(invalid(&format!("Unknown command {}", name))),
+    };
+    Ok(Some(command))
+}
+
+fn is_tag(word: &str) -> bool {
+    (word.starts_with('+') || word.starts_with('@'))
+        && word.len() > 1
+        && !word.contains(char::is_whitespace)
+}
+
+/// Splits a line of a script into arguments like a shell does, with `"..."` and
+/// `'...'` quoting and `\` escaping the next character.
+pub fn split_line(line: &str) -> Result<Vec<String>, String> {
+    let mut args = Vec::new();
+    let mut arg: Option<String> = None;
+    let mut quote: Option<char> = None;
+    let mut chars = line.chars();
+
+    while let Some(c) = chars.next() {
+        match (c, quote) {
+            ('\\', Some('\'')) => arg.get_or_insert_with(String::new).push(c),
+            ('\\', _) => match chars.next() {
+                Some(c) => arg.get_or_insert_with(String::new).push(c),
+                None => return Err("\\ at the end of the line".to_string()),
+            },
+            (c, Some(open)) if c == open => quote = None,
+            (c, Some(_)) => arg.get_or_insert_with(String::new).push(c),
+            ('"' | '\'', None) => {
+                quote = Some(c);
+                arg.get_or_insert_with(String::new);
+            }
+            (c, None) if c.is_whitespace() => args.extend(arg.take()),
+            (c, None) => arg.get_or_insert_with(String::new).push(c),
+        }
+    }
+    if let Some(quote) = quote {
+        return Err(format!("{} isn't closed", quote));
+    }
+    args.extend(arg);
+    Ok(args)
+}
+
+/// Runs the commands of a script, one per line. Empty lines and ones starting with
+/// `#` are skipped. Either all commands succeed or the TODOs are left unchanged and
+/// the error tells the line which failed.
+pub fn run_script(script: &str, todos: &mut Vec<Todo>) -> Result<Outcome, Error> {
+    let mut changed = todos.clone();
+    let mut outcomes = Vec::new();
+    for (i, line) in script.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let run = |changed: &mut Vec<Todo>| {
+            let args = split_line(line).map_err(Error::Usage)?;
+            match parse(&args)? {
+                Some(Command::Batch { .. }) | Some(Command::Doctor { .. }) => Err(Error::Usage(
+                    format!("{} can't be run in a script", args[0]),
+                )),
+                Some(command) => command.run(changed),
+                None => unreachable!("the line isn't empty"),
+            }
+        };
+        outcomes.push(run(&mut changed).map_err(|err| err.at_line(i + 1))?);
+    }
+    *todos = changed;
+    Ok(Outcome::Batch(outcomes))
+}
+
+impl Command {
+    pub fn needs_write_access(&self) -> bool {
+        !matches!(
+            self,
+            Command::List { .. } | Command::Doctor { dry_run: true }
+        )
+    }
+
+    /// The command for the TODO with the id, if it is one for a single TODO.
+    fn with_id(&self, id: String) -> Command {
+        let mut command = self.clone();
+        if let Command::Done { id: target }
+        | Command::Edit { id: target, .. }
+        | Command::Rm { id: target }
+        | Command::Tag { id: target, .. } = &mut command
+        {
+            *target = id;
+        }
+        command
+    }
+
+    /// Runs a command on the TODOs. `doctor` works on the stored data, not on them.
+    pub fn run(&self, todos: &mut Vec<Todo>) -> Result<Outcome, Error> {
+        let outcome = match self {
+            Command::Add { text } => {
+                let todo = Todo::new(text.clone());
+                todos.push(todo.clone());
+                Outcome::Added(todos.len(), todo)
+            }
+            Command::List { filter } => {
+                let today = Local::now().date_naive();
+                Outcome::List(
+                    todos
+                        .iter()
+                        .enumerate()
+                        .filter(|(_, todo)| {
+                            filter
+                                .as_ref()
+                                .is_none_or(|filter| filter.matches(todo, today))
+                        })
+                        .map(|(i, todo)| (i + 1, todo.clone()))
+                        .collect(),
+                )
+            }
+            Command::Bulk { filter, command } => {
+                let today = Local::now().date_naive();
+                let ids: Vec<String> = todos
+                    .iter()
+                    .filter(|todo| filter.matches(todo, today))
+                    .map(|todo| todo.id.to_string())
+                    .collect();
+                let outcomes = ids
+                    .into_iter()
+                    .map(|id| command.with_id(id).run(todos))
+                    .collect::<Result<_, _>>()?;
+                Outcome::Batch(outcomes)
+            }
+            Command::Done { id } => {
+                let index = find_todo(todos, id)?;
+                let todo = &mut todos[index];
+                todo.set_completed(true, Local::now().date_naive());
+                Outcome::Completed(index + 1, todo.clone())
+            }
+            Command::Edit {
+                id,
+                text,
+                completed,
+            } => {
+                let index = find_todo(todos, id)?;
+                let todo = &mut todos[index];
+                if let Some(text) = text {
+                    todo.text = text.clone();
+                }
+                if let Some(completed) = completed {
+                    todo.set_completed(*completed, Local::now().date_naive());
+                }
+                Outcome::Edited(index + 1, todo.clone())
+            }
+            Command::Rm { id } => {
+                let index = find_todo(todos, id)?;
+                Outcome::Removed(index + 1, remove_todo(todos, index))
+            }
+            Command::Tag { id, tags } => {
+                let index = find_todo(todos, id)?;
+                let todo = &mut todos[index];
+                for tag in tags {
+                    if !todo.text.split_whitespace().any(|word| word == tag) {
+                        todo.text = format!("{} {}", todo.text, tag);
+                    }
+                }
+                Outcome::Tagged(index + 1, todo.clone())
+            }
+            Command::Batch { path } => {
+                let script = match path {
+                    Some(path) => std::fs::read_to_string(path).map_err(|err| {
+                        ApplicationError(format!("Can't read {}: {}", path.display(), err))
+                    })?,
+                    None => {
+                        let mut script = String::new();
+                        io::stdin()
+                            .read_to_string(&mut script)
+                            .map_err(ApplicationError::from)?;
+                        script
+                    }
+                };
+                return run_script(&script, todos);
+            }
+            Command::Doctor { .. } => {
+                return Err(
+                    ApplicationError("doctor doesn't work on loaded TODOs".to_string()).into(),
+                )
+            }
+        };
+        Ok(outcome)
+    }
+}
+
+impl Outcome {
+    /// The outcome as printed in the format.
+    pub fn render(&self, format: OutputFormat, templates: &Templates) -> String {
+        match format {
+            OutputFormat::Text => self.describe(templates),
+            format => output::outcome(self, format),
+        }
+    }
+
+    /// The text printed for the outcome.
+    pub fn describe(&self, templates: &Templates) -> String {
+        match self {
+            Outcome::List(todos) => todos
+                .iter()
+                .map(|(number, todo)| format!("# {}: {}\n", number, templates.list.render(todo)))
+                .collect(),
+            Outcome::Added(_, todo) => format!("Added TODO {}\n", todo.id),
+            Outcome::Completed(_, todo) => format!("Completed TODO {}\n", todo.id),
+            Outcome::Edited(_, todo) => {
+                format!("Edited TODO {}: {}\n", todo.id, templates.list.render(todo))
+            }
+            Outcome::Removed(_, todo) => format!("Deleted TODO {}\n", todo.id),
+            Outcome::Tagged(_, todo) => {
+                format!("Tagged TODO {}: {}\n", todo.id, templates.list.render(todo))
+            }
+            Outcome::Batch(outcomes) => outcomes
+                .iter()
+                .map(|outcome| outcome.describe(templates))
+                .collect(),
+            Outcome::Doctor {
+                findings,
+                repairable,
+                dry_run,
+                backup,
+                content_checked,
+            } => {
+                let mut text = String::new();
+                if !content_checked {
+                    text += "Only the TODOs the backend loaded were checked, not its files.\n";
+                }
+                if findings.is_empty() {
+                    return text + "No problems found.\n";
+                }
+                for finding in findings {
+                    match &finding.fix {
+                        Some(fix) => text += &format!("{}: {}\n", finding.problem, fix),
+                        None => {
+                            text +=
+                                &format!("{}: can't be repaired automatically\n", finding.problem)
+                        }
+                    }
+                }
+                if !repairable {
+                    text += "The data has to be repaired by hand.\n";
+                } else if *dry_run {
+                    text += "Dry run, nothing was changed.\n";
+                } else {
+                    if let Some(backup) = backup {
+                        text += &format!("Backed up the data to {}\n", backup.display());
+                    }
+                    text += &format!("Repaired {} problem(s).\n", findings.len());
+                }
+                text
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(parse(&[]).unwrap(), None);
+        assert_eq!(
+            parse(&args(&["add", "Buy milk"])).unwrap(),
+            Some(Command::Add {
+                text: "Buy milk".to_string()
+            })
+        );
+        assert_eq!(
+            parse(&args(&[
+                "edit",
+                "2",
+                "--completed",
+                "false",
+                "--text",
+                "new"
+            ]))
+            .unwrap(),
+            Some(Command::Edit {
+                id: "2".to_string(),
+                text: Some("new".to_string()),
+                completed: Some(false),
+            })
+        );
+        assert_eq!(
+            parse(&args(&["doctor", "--dry-run"])).unwrap(),
+            Some(Command::Doctor { dry_run: true })
+        );
+        for invalid in [
+            &["add"][..],
+            &["add", "a", "b"],
+            &["edit", "1"],
+            &["edit", "1", "--text"],
+            &["edit", "1", "--completed", "yes"],
+            &["edit", "1", "--priority", "A"],
+            &["list", "--all"],
+            &["remove", "1"],
+        ] {
+            assert!(parse(&args(invalid)).is_err(), "{:?}", invalid);
+        }
+
+        let mut with_format = args(&["list", "--format", "jsonl"]);
+        assert_eq!(take_format(&mut with_format).unwrap(), OutputFormat::Jsonl);
+        assert_eq!(with_format, args(&["list"]));
+        assert!(take_format(&mut args(&["list", "--format", "xml"])).is_err());
+        assert!(take_format(&mut args(&["list", "--format"])).is_err());
+    }
+
+    #[test]
+    fn test_run() {
+        let mut todos = vec![Todo::new("first".to_string())];
+        let Outcome::Added(2, added) = Command::Add {
+            text: "second".to_string(),
+        }
+        .run(&mut todos)
+        .unwrap() else {
+            panic!("not added");
+        };
+        let mut child = Todo::new("child".to_string());
+        child.parent = Some(added.id);
+        todos.push(child);
+
+        let id = added.id.to_string();
+        let outcome = Command::Done { id: id.clone() }.run(&mut todos).unwrap();
+        assert!(matches!(outcome, Outcome::Completed(2, todo) if todo.id == added.id));
+        assert!(todos[1].completed);
+        assert_eq!(todos[1].completed_at, Some(Local::now().date_naive()));
+
+        let edit = Command::Edit {
+            id: "1".to_string(),
+            text: Some("changed".to_string()),
+            completed: None,
+        };
+        edit.run(&mut todos).unwrap();
+        assert_eq!(todos[0].text, "changed");
+        let reopen = Command::Edit {
+            id: "2".to_string(),
+            text: None,
+            completed: Some(false),
+        };
+        reopen.run(&mut todos).unwrap();
+        assert!(!todos[1].completed);
+        assert_eq!(todos[1].completed_at, None);
+
+        for id in ["0", "4", "zzz"] {
+            let err = Command::Rm { id: id.to_string() }
+                .run(&mut todos)
+                .unwrap_err();
+            assert_eq!(err.code(), "invalid_selection");
+        }
+        let outcome = Command::Rm { id }.run(&mut todos).unwrap();
+        assert!(matches!(outcome, Outcome::Removed(2, todo) if todo.id == added.id));
+        assert_eq!(todos.len(), 2);
+        assert_eq!(todos[1].parent, None);
+
+        let listed = Command::List { filter: None }.run(&mut todos).unwrap();
+        assert_eq!(
+            listed.describe(&Templates::default()),
+            "# 1: completed: false | text: changed\n\
+             # 2: completed: false | text: child\n"
+        );
+    }
+
+    #[test]
+    fn test_select() {
+        let mut todos: Vec<Todo> = (1..=3).map(|i| Todo::new(format!("todo {}", i))).collect();
+        todos[0].id = Uuid::parse_str("30000000-0000-4000-8000-000000000000").unwrap();
+        todos[1].id = Uuid::parse_str("00020000-0000-4000-8000-000000000000").unwrap();
+        todos[2].id = Uuid::parse_str("0003a000-0000-4000-8000-000000000000").unwrap();
+
+        // Short numbers are never ids, longer ones fall back to ids
+        assert_eq!(find_todo(&todos, "3").ok(), Some(2));
+        assert_eq!(find_todo(&todos, "30000000").ok(), Some(0));
+        assert_eq!(find_todo(&todos, "0002").ok(), Some(1));
+        assert_eq!(find_todo(&todos, "0003A").ok(), Some(2));
+        assert!(find_todo(&todos, "000").is_err());
+        assert!(find_todo(&todos, "0000").is_err());
+        assert!(find_todo(&todos, "4").is_err());
+
+        todos[1].id = Uuid::parse_str("00030000-0000-4000-8000-000000000000").unwrap();
+        assert!(find_todo(&todos, "0003").is_err());
+
+        // Both a number and the start of an id
+        todos.resize_with(1000, || Todo::new("more".to_string()));
+        todos[0].id = Uuid::parse_str("10000000-0000-4000-8000-000000000000").unwrap();
+        let err = find_todo(&todos, "1000").unwrap_err();
+        assert!(err.0.contains("give more of the id"), "{}", err.0);
+        assert_eq!(find_todo(&todos, "10000").ok(), Some(0));
+    }
+
+    #[test]
+    fn test_script() {
+        assert_eq!(
+            split_line(r#"add "Buy \"good\" milk" 'it\s' a\ b"#).unwrap(),
+            vec!["add", "Buy \"good\" milk", "it\\s", "a b"]
+        );
+        assert_eq!(split_line("  rm ''  ").unwrap(), vec!["rm", ""]);
+        assert!(split_line("add \"open").is_err());
+
+        let script = "# Release checklist\n\
+                      add \"Write changelog\"\n\
+                      \n\
+                      add 'Tag release'\n\
+                      tag 2 +release @git\n\
+                      done 1\n";
+        let mut todos = vec![Todo::new("existing".to_string())];
+        let Outcome::Batch(outcomes) = run_script(script, &mut todos).unwrap() else {
+            panic!("no batch");
+        };
+        assert_eq!(outcomes.len(), 4);
+        assert_eq!(todos.len(), 3);
+        assert!(todos[0].completed);
+        assert_eq!(todos[1].text, "Write changelog +release @git");
+
+        // A failing line rolls back the whole script
+        let before = todos.clone();
+        let err = run_script("add first\ntag 2 +x\n\ndone 9\n", &mut todos).unwrap_err();
+        assert_eq!(err.code(), "invalid_selection");
+        assert_eq!(err.message(), "Line 4: There is no TODO 9");
+        assert_eq!(todos, before);
+        let err = run_script("add ok\ndoctor\n", &mut todos).unwrap_err();
+        assert_eq!(err.message(), "Line 2: doctor can't be run in a script");
+        assert_eq!(todos, before);
+    }
+
+    #[test]
+    fn test_filter() {
+        let err = parse(&args(&["rm", "--where", "status:"])).unwrap_err();
+        assert_eq!(err.code(), "invalid_arguments");
+        assert!(parse(&args(&["edit", "--where", "status:open"])).is_err());
+
+        let mut todos: Vec<Todo> = ["Deploy +release", "Fix bug", "Write notes +release"]
+            .into_iter()
+            .map(|text| Todo::new(text.to_string()))
+            .collect();
+        let list = parse(&args(&["list", "tag:release"])).unwrap().unwrap();
+        let Outcome::List(listed) = list.run(&mut todos).unwrap() else {
+            panic!("not listed");
+        };
+        // They keep the numbers to select them with
+        let numbers: Vec<usize> = listed.iter().map(|(number, _)| *number).collect();
+        assert_eq!(numbers, vec![1, 3]);
+
+        let bulk = parse(&args(&["tag", "--where", "tag:release", "@work"]))
+            .unwrap()
+            .unwrap();
+        assert!(
+            matches!(&bulk, Command::Bulk { command, .. } if matches!(**command, Command::Tag { .. }))
+        );
+        bulk.run(&mut todos).unwrap();
+        let bulk = parse(&args(&["rm", "--where", "tag:release"]))
+            .unwrap()
+            .unwrap();
+        let Outcome::Batch(removed) = bulk.run(&mut todos).unwrap() else {
+            panic!("no batch");
+        };
+        assert_eq!(removed.len(), 2);
+        assert!(
+            matches!(&removed[1], Outcome::Removed(_, todo) if todo.text == "Write notes +release @work")
+        );
+        assert_eq!(todos.len(), 1);
+        assert_eq!(todos[0].text, "Fix bug");
+    }
+}
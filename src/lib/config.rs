@@ -0,0 +1,82 @@
+use crate::{
+    crdt::ReplicaConfig, errors::ApplicationError, formats::csv::CsvConfig, git::GitConfig,
+    snapshot, storage::StorageConfig, template::TemplateConfig,
+};
+use serde::Deserialize;
+use std::{
+    io::ErrorKind,
+    path::{Path, PathBuf},
+};
+
+pub const CONFIG_FILE: &str = "todo_config.json";
+
+/// Settings read from `todo_config.json`. Everything is optional, a missing file
+/// means the defaults.
+#[derive(Deserialize, Debug, Default, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub storage: StorageConfig,
+    /// Keep the data in a git repository, off if not set
+    pub git: Option<GitConfig>,
+    /// Track changes in a CRDT replica to sync with other machines, off if not set
+    pub replica: Option<ReplicaConfig>,
+    /// Directory of the snapshots, `snapshots` if not set
+    pub snapshots: Option<PathBuf>,
+    /// Columns of CSV exports, all fields if not set
+    pub csv: CsvConfig,
+    /// Named formats of listed and exported TODOs
+    pub templates: TemplateConfig,
+}
+
+impl Config {
+    pub fn load(path: &Path) -> Result<Config, ApplicationError> {
+        let invalid = |err: &dyn std::fmt::Display| {
+            ApplicationError(format!("Invalid config {}: {}", path.display(), err))
+        };
+        let config: Config = match std::fs::read(path) {
+            Ok(content) => serde_json::from_slice(&content).map_err(|err| invalid(&err))?,
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(Config::default()),
+            Err(err) => return Err(err.into()),
+        };
+        // The replica holds all TODOs as plain JSON
+        if config.replica.is_some() && config.storage.is_encrypted() {
+            return Err(invalid(&"the replica can't be used with encrypted storage"));
+        }
+        Ok(config)
+    }
+
+    pub fn snapshot_dir(&self) -> PathBuf {
+        self.snapshots
+            .clone()
+            .unwrap_or_else(|| PathBuf::from(snapshot::DEFAULT_DIR))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load() {
+        let path = std::env::temp_dir().join(format!("todo_config_{}.json", uuid::Uuid::new_v4()));
+        assert_eq!(Config::load(&path).unwrap(), Config::default());
+
+        std::fs::write(
+            &path,
+            r#"{"storage": {"backend": "json", "path": "todos.json", "encrypted": true},
+                "replica": {"path": "replica.json"}}"#,
+        )
+        .unwrap();
+        let err = Config::load(&path).unwrap_err();
+        assert!(
+            err.0.contains("can't be used with encrypted storage"),
+            "{}",
+            err.0
+        );
+
+        std::fs::write(&path, r#"{"replica": {"path": "replica.json"}}"#).unwrap();
+        assert!(Config::load(&path).unwrap().replica.is_some());
+
+        let _ = std::fs::remove_file(path);
+    }
+}
@@ -0,0 +1,369 @@
+use crate::{errors::ApplicationError, todo::Todo};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    io::ErrorKind,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+use uuid::Uuid;
+
+/// The `replica` section of the config file. If present, every change is tracked in
+/// a replica at `path`, which can be merged with replicas of other machines.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct ReplicaConfig {
+    pub path: PathBuf,
+}
+
+/// Hybrid logical timestamp. Ordered by wall clock time, a counter for changes in the
+/// same millisecond and the replica id as tie breaker, so no two replicas ever
+/// produce the same stamp.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Stamp {
+    pub time: u64,
+    pub counter: u32,
+    pub replica: Uuid,
+}
+
+/// Last-writer-wins register.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct Lww<T> {
+    pub value: T,
+    pub stamp: Stamp,
+}
+
+impl<T: Clone + PartialEq> Lww<T> {
+    fn new(value: T, stamp: Stamp) -> Self {
+        Self { value, stamp }
+    }
+
+    /// Sets a different `value` with a new stamp.
+    fn assign(&mut self, value: &T, tick: &mut impl FnMut() -> Stamp) {
+        if self.value != *value {
+            *self = Lww::new(value.clone(), tick());
+        }
+    }
+
+    fn merge(&mut self, other: &Self) {
+        if other.stamp > self.stamp {
+            *self = other.clone();
+        }
+    }
+}
+
+/// The fields of a TODO, each merged on its own.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+struct TodoState {
+    /// When the TODO was first added, orders TODOs unknown to the local list
+    added: Stamp,
+    text: Lww<String>,
+    completed: Lww<bool>,
+    #[serde(default)]
+    priority: Lww<Option<char>>,
+    #[serde(default)]
+    created: Lww<Option<NaiveDate>>,
+    #[serde(default)]
+    completed_at: Lww<Option<NaiveDate>>,
+    #[serde(default)]
+    list: Lww<Option<String>>,
+    #[serde(default)]
+    parent: Lww<Option<Uuid>>,
+    /// Merged as a whole, the keys are hardly ever edited on their own
+    #[serde(default)]
+    metadata: Lww<BTreeMap<String, String>>,
+}
+
+impl TodoState {
+    fn new(todo: &Todo, stamp: Stamp) -> Self {
+        Self {
+            added: stamp,
+            text: Lww::new(todo.text.clone(), stamp),
+            completed: Lww::new(todo.completed, stamp),
+            priority: Lww::new(todo.priority, stamp),
+            created: Lww::new(todo.created, stamp),
+            completed_at: Lww::new(todo.completed_at, stamp),
+            list: Lww::new(todo.list.clone(), stamp),
+            parent: Lww::new(todo.parent, stamp),
+            metadata: Lww::new(todo.metadata.clone(), stamp),
+        }
+    }
+
+    fn todo(&self, id: Uuid) -> Todo {
+        Todo {
+            id,
+            text: self.text.value.clone(),
+            completed: self.completed.value,
+            priority: self.priority.value,
+            created: self.created.value,
+            completed_at: self.completed_at.value,
+            list: self.list.value.clone(),
+            parent: self.parent.value,
+            metadata: self.metadata.value.clone(),
+        }
+    }
+
+    fn update(&mut self, todo: &Todo, tick: &mut impl FnMut() -> Stamp) {
+        self.text.assign(&todo.text, tick);
+        self.completed.assign(&todo.completed, tick);
+        self.priority.assign(&todo.priority, tick);
+        self.created.assign(&todo.created, tick);
+        self.completed_at.assign(&todo.completed_at, tick);
+        self.list.assign(&todo.list, tick);
+        self.parent.assign(&todo.parent, tick);
+        self.metadata.assign(&todo.metadata, tick);
+    }
+
+    fn merge(&mut self, other: &Self) {
+        self.added = self.added.min(other.added);
+        self.text.merge(&other.text);
+        self.completed.merge(&other.completed);
+        self.priority.merge(&other.priority);
+        self.created.merge(&other.created);
+        self.completed_at.merge(&other.completed_at);
+        self.list.merge(&other.list);
+        self.parent.merge(&other.parent);
+        self.metadata.merge(&other.metadata);
+    }
+}
+
+/// The TODO list as a CRDT: membership is an observed-remove set, the fields are
+/// last-writer-wins registers. Merging two replicas in any order gives the same state.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Replica {
+    /// Id of the local replica, part of every stamp it makes
+    id: Uuid,
+    /// Latest stamp made or seen
+    clock: Stamp,
+    /// Tags of every add of a TODO. It is in the list while it has tags that were not removed
+    adds: BTreeMap<Uuid, BTreeSet<Stamp>>,
+    /// Add tags observed by a remove
+    removed: BTreeSet<Stamp>,
+    todos: BTreeMap<Uuid, TodoState>,
+}
+
+impl Replica {
+    pub fn new() -> Self {
+        let id = Uuid::new_v4();
+        Self {
+            id,
+            clock: Stamp {
+                time: 0,
+                counter: 0,
+                replica: id,
+            },
+            adds: BTreeMap::new(),
+            removed: BTreeSet::new(),
+            todos: BTreeMap::new(),
+        }
+    }
+
+    /// Reads a replica, a new one if there is no file yet.
+    pub fn load(path: &Path) -> Result<Self, ApplicationError> {
+        match std::fs::read(path) {
+            Ok(content) => Ok(serde_json::from_slice(&content)?),
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(Self::new()),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Writes the replica, unless the file already has the same state.
+    pub fn save(&self, path: &Path) -> Result<(), ApplicationError> {
+        let content = serde_json::to_vec(self)?;
+        if std::fs::read(path).is_ok_and(|stored| stored == content) {
+            return Ok(());
+        }
+        let mut tmp_path = path.as_os_str().to_owned();
+        tmp_path.push(".tmp");
+        std::fs::write(&tmp_path, content)?;
+        std::fs::rename(&tmp_path, path)?;
+        Ok(())
+    }
+
+    fn tick(&mut self) -> Stamp {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |time| time.as_millis() as u64);
+        self.clock = if now > self.clock.time {
+            Stamp {
+                time: now,
+                counter: 0,
+                replica: self.id,
+            }
+        } else {
+            Stamp {
+                time: self.clock.time,
+                counter: self.clock.counter + 1,
+                replica: self.id,
+            }
+        };
+        self.clock
+    }
+
+    fn contains(&self, id: &Uuid) -> bool {
+        self.adds
+            .get(id)
+            .is_some_and(|tags| tags.iter().any(|tag| !self.removed.contains(tag)))
+    }
+
+    /// Records the differences between the replica and `todos` as local changes.
+    pub fn update(&mut self, todos: &[Todo]) {
+        for todo in todos {
+            if !self.contains(&todo.id) {
+                let tag = self.tick();
+                self.adds.entry(todo.id).or_default().insert(tag);
+            }
+
+            match self.todos.get(&todo.id) {
+                Some(state) if state.todo(todo.id) == *todo => {}
+                Some(state) => {
+                    let mut state = state.clone();
+                    state.update(todo, &mut || self.tick());
+                    self.todos.insert(todo.id, state);
+                }
+                None => {
+                    let state = TodoState::new(todo, self.tick());
+                    self.todos.insert(todo.id, state);
+                }
+            }
+        }
+
+        let removed: Vec<Uuid> = self
+            .adds
+            .keys()
+            .filter(|id| !todos.iter().any(|todo| todo.id == **id))
+            .copied()
+            .collect();
+        for id in removed {
+            if let Some(tags) = self.adds.get(&id) {
+                self.removed.extend(tags.iter().copied());
+            }
+        }
+    }
+
+    /// Merges the state of `other` into this replica.
+    pub fn merge(&mut self, other: &Replica) {
+        for (id, tags) in &other.adds {
+            self.adds
+                .entry(*id)
+                .or_default()
+                .extend(tags.iter().copied());
+        }
+        self.removed.extend(other.removed.iter().copied());
+        for (id, state) in &other.todos {
+            match self.todos.get_mut(id) {
+                Some(local) => local.merge(state),
+                None => {
+                    self.todos.insert(*id, state.clone());
+                }
+            }
+        }
+
+        // Stamps made after the merge must be newer than everything seen
+        let latest = other.clock.max(self.clock);
+        self.clock = Stamp {
+            replica: self.id,
+            ..latest
+        };
+    }
+
+    /// The TODOs of the replica. Those also in `local` keep its order, the others are
+    /// appended in the order they were created.
+    pub fn todos(&self, local: &[Todo]) -> Vec<Todo> {
+        let mut ids: Vec<Uuid> = local
+            .iter()
+            .map(|todo| todo.id)
+            .filter(|id| self.contains(id))
+            .collect();
+        let mut others: Vec<(&Stamp, &Uuid)> = self
+            .todos
+            .iter()
+            .filter(|(id, _)| self.contains(id) && !ids.contains(id))
+            .map(|(id, state)| (&state.added, id))
+            .collect();
+        others.sort();
+        ids.extend(others.into_iter().map(|(_, id)| *id));
+
+        ids.iter()
+            .filter_map(|id| self.todos.get(id).map(|state| state.todo(*id)))
+            .collect()
+    }
+}
+
+impl Default for Replica {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn merged(a: &Replica, b: &Replica) -> Vec<Todo> {
+        let mut result = a.clone();
+        result.merge(b);
+        result.todos(&[])
+    }
+
+    #[test]
+    fn test_merge_converges() {
+        let first = Todo::new("first".to_string());
+        let second = Todo::new("second".to_string());
+        let mut a = Replica::new();
+        a.update(&[first.clone(), second.clone()]);
+        let mut b = Replica::new();
+        b.merge(&a);
+        assert_eq!(b.todos(&[]), vec![first.clone(), second.clone()]);
+
+        // Concurrently: a edits the text and deletes the second, b completes the
+        // first, edits the second and adds a third
+        let mut edited = first.clone();
+        edited.text = "first edited".to_string();
+        a.update(std::slice::from_ref(&edited));
+
+        let mut completed = first.clone();
+        completed.completed = true;
+        let mut second_edited = second.clone();
+        second_edited.text = "second edited".to_string();
+        let third = Todo::new("third".to_string());
+        b.update(&[completed, second_edited, third.clone()]);
+
+        let result = merged(&a, &b);
+        assert_eq!(result, merged(&b, &a));
+        let mut expected = edited.clone();
+        expected.completed = true;
+        assert_eq!(result, vec![expected, third]);
+    }
+
+    #[test]
+    fn test_last_writer_wins_and_readd() {
+        let first = Todo::new("first".to_string());
+        let mut a = Replica::new();
+        a.update(std::slice::from_ref(&first));
+        let mut b = a.clone();
+        b.id = Uuid::new_v4();
+
+        let mut by_a = first.clone();
+        by_a.text = "by a".to_string();
+        a.update(std::slice::from_ref(&by_a));
+        let mut by_b = first.clone();
+        by_b.text = "by b".to_string();
+        b.update(std::slice::from_ref(&by_b));
+
+        // Whichever wins, both replicas agree on it
+        let result = merged(&a, &b);
+        assert_eq!(result, merged(&b, &a));
+        assert_eq!(result.len(), 1);
+
+        // Deleting and adding again (e.g. restoring it) concurrently keeps it
+        a.update(&[]);
+        b.update(std::slice::from_ref(&by_b));
+        let mut readded = b.clone();
+        readded.update(&[]);
+        readded.update(std::slice::from_ref(&by_b));
+        assert_eq!(merged(&a, &readded).len(), 1);
+        assert_eq!(merged(&readded, &a).len(), 1);
+    }
+}
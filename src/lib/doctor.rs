@@ -0,0 +1,490 @@
+use crate::{
+    errors::ApplicationError,
+    storage::{schema, Storage},
+    todo::{Todo, DUE_KEY},
+};
+use chrono::NaiveDate;
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use uuid::Uuid;
+
+const FIELDS: [&str; 9] = [
+    "id",
+    "text",
+    "completed",
+    "priority",
+    "created",
+    "completed_at",
+    "list",
+    "parent",
+    "metadata",
+];
+
+/// A problem found in the data and how it gets repaired, `None` if it can't be.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Finding {
+    pub problem: String,
+    pub fix: Option<String>,
+}
+
+#[derive(Debug, Default)]
+pub struct Report {
+    pub findings: Vec<Finding>,
+    /// The repaired TODOs, `None` if a problem can't be repaired automatically
+    pub todos: Option<Vec<Todo>>,
+    /// Whether the stored data was checked as it is, not only the TODOs loaded from it
+    pub content_checked: bool,
+}
+
+impl Report {
+    fn found(&mut self, problem: String, fix: &str) {
+        self.findings.push(Finding {
+            problem,
+            fix: Some(fix.to_string()),
+        });
+    }
+
+    fn unfixable(mut self, problem: String) -> Self {
+        self.findings.push(Finding { problem, fix: None });
+        self.todos = None;
+        self
+    }
+}
+
+/// Checks the data of `storage`. Backends without a single data file are only checked
+/// as far as they can load the TODOs, if they can't, that is reported.
+pub fn check_storage(storage: &mut dyn Storage) -> Result<Report, ApplicationError> {
+    if let Some(content) = storage.load_raw()? {
+        return Ok(check_content(&content));
+    }
+    Ok(match storage.load() {
+        Ok(todos) => check_todos(&todos),
+        Err(err) => Report::default().unfixable(format!("The data can't be loaded: {}", err)),
+    })
+}
+
+/// Validates the content of a data file, down to the bytes, and repairs what it can.
+pub fn check_content(content: &[u8]) -> Report {
+    let mut report = Report {
+        content_checked: true,
+        ..Default::default()
+    };
+
+    let text = match std::str::from_utf8(content) {
+        Ok(text) => text.to_string(),
+        Err(err) => {
+            report.found(
+                format!("Invalid UTF-8 at byte {}", err.valid_up_to()),
+                "Replace the invalid bytes with \u{FFFD}",
+            );
+            String::from_utf8_lossy(content).into_owned()
+        }
+    };
+
+    let data: Value = match serde_json::from_str(&text) {
+        Ok(data) => data,
+        Err(err) => return report.unfixable(format!("The data file is not valid JSON: {}", err)),
+    };
+    let todos = match schema::migrate(data) {
+        Ok(Value::Array(todos)) => todos,
+        Ok(_) => return report.unfixable("The TODOs are not a list".to_string()),
+        Err(err) => return report.unfixable(err.0),
+    };
+
+    let todos = todos
+        .into_iter()
+        .enumerate()
+        .filter_map(|(i, todo)| Some((i + 1, check_fields(&mut report, i + 1, todo)?)))
+        .collect();
+    let todos = check(&mut report, todos);
+    report.todos = Some(todos);
+    report
+}
+
+/// Validates TODOs loaded by a backend without a single data file.
+pub fn check_todos(todos: &[Todo]) -> Report {
+    let mut report = Report::default();
+    let todos = (1..).zip(todos.iter().cloned()).collect();
+    let todos = check(&mut report, todos);
+    report.todos = Some(todos);
+    report
+}
+
+/// Checks the schema of the TODO at `number`, which isn't known to be valid yet.
+fn check_fields(report: &mut Report, number: usize, todo: Value) -> Option<Todo> {
+    let Value::Object(mut todo) = todo else {
+        report.found(format!("TODO #{} is not an object", number), "Remove it");
+        return None;
+    };
+
+    let id = match todo.get("id").and_then(Value::as_str).map(Uuid::parse_str) {
+        Some(Ok(id)) => id,
+        _ => {
+            report.found(
+                format!("TODO #{} has no valid id", number),
+                "Assign a new id",
+            );
+            Uuid::new_v4()
+        }
+    };
+
+    let text = match todo.remove("text") {
+        Some(Value::String(text)) => text,
+        Some(Value::Null) | None => {
+            report.found(format!("TODO #{} has no text", number), "Set an empty text");
+            String::new()
+        }
+        Some(other) => {
+            report.found(
+                format!("TODO #{} has a text which is no string", number),
+                "Convert it to a string",
+            );
+            other.to_string()
+        }
+    };
+
+    let completed = match todo.get("completed") {
+        Some(Value::Bool(completed)) => *completed,
+        _ => {
+            report.found(
+                format!("TODO #{} has no valid completed state", number),
+                "Mark it as not completed",
+            );
+            false
+        }
+    };
+
+    let priority = match todo.get("priority") {
+        None | Some(Value::Null) => None,
+        Some(Value::String(priority)) if is_priority(priority) => priority.chars().next(),
+        Some(_) => {
+            report.found(
+                format!("TODO #{} has an invalid priority", number),
+                "Remove the priority",
+            );
+            None
+        }
+    };
+
+    let created = check_date(report, number, todo.get("created"), "creation");
+    let completed_at = check_date(report, number, todo.get("completed_at"), "completion");
+
+    let list = match todo.get("list") {
+        None | Some(Value::Null) => None,
+        Some(Value::String(list)) if !list.trim().is_empty() => Some(list.clone()),
+        Some(_) => {
+            report.found(
+                format!("TODO #{} has an invalid list name", number),
+                "Move it to the default list",
+            );
+            None
+        }
+    };
+
+    let parent = match todo.get("parent") {
+        None | Some(Value::Null) => None,
+        Some(parent) => match parent.as_str().map(Uuid::parse_str) {
+            Some(Ok(parent)) => Some(parent),
+            _ => {
+                report.found(
+                    format!("TODO #{} has an invalid parent", number),
+                    "Remove the parent",
+                );
+                None
+            }
+        },
+    };
+
+    let mut metadata = BTreeMap::new();
+    match todo.get("metadata") {
+        None | Some(Value::Null) => {}
+        Some(Value::Object(values)) => {
+            for (key, value) in values {
+                let value = match value {
+                    Value::String(value) => value.clone(),
+                    other => {
+                        report.found(
+                            format!("TODO #{} has a metadata value which is no string", number),
+                            "Convert it to a string",
+                        );
+                        other.to_string()
+                    }
+                };
+                metadata.insert(key.clone(), value);
+            }
+        }
+        Some(_) => report.found(
+            format!("TODO #{} has invalid metadata", number),
+            "Remove the metadata",
+        ),
+    }
+
+    for key in todo.keys().filter(|key| !FIELDS.contains(&key.as_str())) {
+        report.found(
+            format!("TODO #{} has the unknown field \"{}\"", number, key),
+            "Remove the field",
+        );
+    }
+
+    Some(Todo {
+        id,
+        text,
+        completed,
+        priority,
+        created,
+        completed_at,
+        list,
+        parent,
+        metadata,
+    })
+}
+
+fn is_priority(priority: &str) -> bool {
+    let mut chars = priority.chars();
+    chars.next().is_some_and(|c| c.is_ascii_uppercase()) && chars.next().is_none()
+}
+
+fn check_date(
+    report: &mut Report,
+    number: usize,
+    date: Option<&Value>,
+    name: &str,
+) -> Option<NaiveDate> {
+    let parsed = match date {
+        None | Some(Value::Null) => return None,
+        Some(Value::String(date)) => NaiveDate::parse_from_str(date, "%Y-%m-%d").ok(),
+        Some(_) => None,
+    };
+    if parsed.is_none() {
+        report.found(
+            format!("TODO #{} has an invalid {} date", number, name),
+            "Remove the date",
+        );
+    }
+    parsed
+}
+
+/// Checks the content and the references of schema-valid TODOs, given with their
+/// number in the data.
+fn check(report: &mut Report, todos: Vec<(usize, Todo)>) -> Vec<Todo> {
+    let mut seen: HashMap<Uuid, Todo> = HashMap::new();
+    let mut result = Vec::new();
+    let mut numbers = Vec::new();
+
+    for (number, mut todo) in todos {
+        match seen.get(&todo.id) {
+            Some(first) if *first == todo => {
+                report.found(
+                    format!("TODO #{} is a duplicate of an earlier one", number),
+                    "Remove it",
+                );
+                continue;
+            }
+            Some(_) => {
+                report.found(
+                    format!("TODO #{} has the id of an earlier one", number),
+                    "Assign a new id",
+                );
+                todo.id = Uuid::new_v4();
+            }
+            None => {}
+        }
+
+        if todo.text.chars().any(char::is_control) {
+            report.found(
+                format!("TODO #{} has control characters in its text", number),
+                "Replace line breaks and tabs with spaces, remove the others",
+            );
+            todo.text = todo
+                .text
+                .chars()
+                .filter_map(|c| match c {
+                    '\n' | '\r' | '\t' => Some(' '),
+                    c if c.is_control() => None,
+                    c => Some(c),
+                })
+                .collect();
+        }
+
+        // Which would be read as no due date
+        if todo.metadata.contains_key(DUE_KEY) && todo.due().is_none() {
+            report.found(
+                format!("TODO #{} has an invalid due date", number),
+                "Remove the due date",
+            );
+            todo.set_due(None);
+        }
+
+        seen.insert(todo.id, todo.clone());
+        result.push(todo);
+        numbers.push(number);
+    }
+
+    for (i, number) in numbers.into_iter().enumerate() {
+        let Some(parent) = result[i].parent else {
+            continue;
+        };
+        if !seen.contains_key(&parent) {
+            report.found(
+                format!("TODO #{} has a parent which doesn't exist", number),
+                "Remove the parent",
+            );
+            result[i].parent = None;
+        } else if is_own_ancestor(&result, i) {
+            report.found(
+                format!("TODO #{} is a subtask of itself", number),
+                "Remove the parent",
+            );
+            result[i].parent = None;
+        }
+    }
+    result
+}
+
+fn is_own_ancestor(todos: &[Todo], index: usize) -> bool {
+    let id = todos[index].id;
+    let mut parent = todos[index].parent;
+    // A chain longer than the list runs through a cycle which doesn't contain `id`
+    for _ in 0..todos.len() {
+        match parent {
+            Some(parent_id) if parent_id == id => return true,
+            Some(parent_id) => {
+                parent = todos
+                    .iter()
+                    .find(|todo| todo.id == parent_id)
+                    .and_then(|todo| todo.parent);
+            }
+            None => return false,
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_content() {
+        let id = Uuid::new_v4();
+        let mut content = format!(
+            r#"{{"version":1,"todos":[
+                {{"id":"{id}","text":"fine","completed":false}},
+                {{"id":"{id}","text":"fine","completed":false}},
+                {{"id":"{id}","text":"same id","completed":true}},
+                {{"id":"nope","text":"tab\there\u0007","completed":"yes","extra":1,"created":"2026-02-30"}},
+                {{"id":"{}","text":"#,
+            Uuid::new_v4()
+        )
+        .into_bytes();
+        content.extend_from_slice(b"\"bad \xff\",\"completed\":true}, 5]}");
+        let invalid = content.iter().position(|byte| *byte == 0xff).unwrap();
+
+        let report = check_content(&content);
+        let problems: Vec<&str> = report
+            .findings
+            .iter()
+            .map(|finding| finding.problem.as_str())
+            .collect();
+        assert_eq!(
+            problems,
+            vec![
+                &format!("Invalid UTF-8 at byte {}", invalid) as &str,
+                "TODO #4 has no valid id",
+                "TODO #4 has no valid completed state",
+                "TODO #4 has an invalid creation date",
+                "TODO #4 has the unknown field \"extra\"",
+                "TODO #6 is not an object",
+                "TODO #2 is a duplicate of an earlier one",
+                "TODO #3 has the id of an earlier one",
+                "TODO #4 has control characters in its text",
+            ]
+        );
+
+        let todos = report.todos.unwrap();
+        assert_eq!(todos.len(), 4);
+        assert_eq!(todos[0].id, id);
+        assert_ne!(todos[1].id, id);
+        assert_eq!(todos[2].text, "tab here");
+        assert!(!todos[2].completed);
+        assert_eq!(todos[3].text, "bad \u{FFFD}");
+
+        let report = check_content(b"{\"version\":99,\"todos\":[]}");
+        assert!(report.todos.is_none());
+        assert_eq!(report.findings[0].fix, None);
+
+        let report = check_todos(&todos);
+        assert!(report.findings.is_empty());
+
+        let mut invalid_due = todos.clone();
+        invalid_due[1]
+            .metadata
+            .insert(DUE_KEY.to_string(), "2026-13-01".to_string());
+        let report = check_todos(&invalid_due);
+        assert_eq!(
+            report.findings,
+            vec![Finding {
+                problem: "TODO #2 has an invalid due date".to_string(),
+                fix: Some("Remove the due date".to_string()),
+            }]
+        );
+        assert_eq!(report.todos.unwrap(), todos);
+
+        // Dangling parents and cycles
+        let mut todos = todos;
+        todos[0].parent = Some(Uuid::new_v4());
+        todos[1].parent = Some(todos[2].id);
+        todos[2].parent = Some(todos[1].id);
+        let report = check_todos(&todos);
+        let problems: Vec<&str> = report
+            .findings
+            .iter()
+            .map(|finding| finding.problem.as_str())
+            .collect();
+        assert_eq!(
+            problems,
+            vec![
+                "TODO #1 has a parent which doesn't exist",
+                "TODO #2 is a subtask of itself",
+            ]
+        );
+        let repaired = report.todos.unwrap();
+        assert_eq!(repaired[0].parent, None);
+        assert_eq!(repaired[1].parent, None);
+        assert_eq!(repaired[2].parent, Some(todos[1].id));
+    }
+
+    #[test]
+    fn test_check_storage() {
+        use crate::storage::{
+            tests::temp_path, DirectoryStorage, EventLogStorage, JsonFileStorage,
+        };
+
+        let dir = temp_path("doctor_storage");
+        let todos = vec![Todo::new("first".to_string())];
+        let mut json = JsonFileStorage::new(dir.join("todos.json"));
+        json.save(&todos).unwrap();
+        assert!(check_storage(&mut json).unwrap().content_checked);
+
+        let mut directory = DirectoryStorage::new(dir.join("directory"));
+        directory.save(&todos).unwrap();
+        let report = check_storage(&mut directory).unwrap();
+        assert!(report.findings.is_empty() && !report.content_checked);
+        std::fs::write(dir.join(format!("directory/{}.json", todos[0].id)), "{").unwrap();
+        let report = check_storage(&mut directory).unwrap();
+        assert!(report.todos.is_none());
+        assert!(report.findings[0]
+            .problem
+            .starts_with("The data can't be loaded"));
+
+        let mut log = EventLogStorage::new(dir.join("log"), 100);
+        log.save(&todos).unwrap();
+        std::fs::write(dir.join("log/events.jsonl"), "garbage\n").unwrap();
+        let report = check_storage(&mut EventLogStorage::new(dir.join("log"), 100)).unwrap();
+        assert!(report.findings[0]
+            .problem
+            .contains("Corrupt event log, line 1"));
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
@@ -10,17 +10,22 @@ impl fmt::Display for ApplicationError {
 }
 impl From<std::io::Error> for ApplicationError {
     fn from(val: std::io::Error) -> Self {
-        Self { 0: val.to_string() }
+        Self(val.to_string())
+    }
+}
+impl From<serde_json::Error> for ApplicationError {
+    fn from(val: serde_json::Error) -> Self {
+        Self(val.to_string())
     }
 }
 impl From<ParseIntError> for ApplicationError {
     fn from(val: ParseIntError) -> Self {
-        Self { 0: val.to_string() }
+        Self(val.to_string())
     }
 }
 impl From<SelectionError> for ApplicationError {
     fn from(val: SelectionError) -> Self {
-        Self { 0: val.to_string() }
+        Self(val.to_string())
     }
 }
 
@@ -0,0 +1,330 @@
+//! Comma separated values with a header row, for spreadsheets.
+
+use crate::{
+    errors::ApplicationError,
+    todo::{Todo, DUE_KEY},
+};
+use chrono::NaiveDate;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use uuid::Uuid;
+
+const DATE_FORMAT: &str = "%Y-%m-%d";
+
+/// A field of `Todo` which can be a column.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Field {
+    Id,
+    Text,
+    Completed,
+    Priority,
+    Created,
+    CompletedAt,
+    /// The due date kept in the metadata
+    Due,
+    List,
+    Parent,
+    /// The unknown `key:value` pairs as a JSON object, without the due date if it has
+    /// its own column
+    Metadata,
+}
+
+impl Field {
+    pub const ALL: [Field; 10] = [
+        Field::Id,
+        Field::Text,
+        Field::Completed,
+        Field::Priority,
+        Field::Created,
+        Field::CompletedAt,
+        Field::Due,
+        Field::List,
+        Field::Parent,
+        Field::Metadata,
+    ];
+
+    /// The name used in the header and config.
+    pub fn name(self) -> &'static str {
+        match self {
+            Field::Id => "id",
+            Field::Text => "text",
+            Field::Completed => "completed",
+            Field::Priority => "priority",
+            Field::Created => "created",
+            Field::CompletedAt => "completed_at",
+            Field::Due => "due",
+            Field::List => "list",
+            Field::Parent => "parent",
+            Field::Metadata => "metadata",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Field> {
+        let name = name.trim().to_lowercase().replace([' ', '-'], "_");
+        Field::ALL.into_iter().find(|field| field.name() == name)
+    }
+
+    fn value(self, todo: &Todo, columns: &[Field]) -> Result<String, ApplicationError> {
+        let date = |date: Option<NaiveDate>| {
+            date.map(|date| date.format(DATE_FORMAT).to_string())
+                .unwrap_or_default()
+        };
+        Ok(match self {
+            Field::Id => todo.id.to_string(),
+            Field::Text => todo.text.clone(),
+            Field::Completed => todo.completed.to_string(),
+            Field::Priority => todo.priority.map(String::from).unwrap_or_default(),
+            Field::Created => date(todo.created),
+            Field::CompletedAt => date(todo.completed_at),
+            Field::Due => date(todo.due()),
+            Field::List => todo.list.clone().unwrap_or_default(),
+            Field::Parent => todo.parent.map(|id| id.to_string()).unwrap_or_default(),
+            Field::Metadata => {
+                // A due date which isn't valid can't be in the due column
+                let due_column = columns.contains(&Field::Due) && todo.due().is_some();
+                let metadata: BTreeMap<&String, &String> = todo
+                    .metadata
+                    .iter()
+                    .filter(|(key, _)| *key != DUE_KEY || !due_column)
+                    .collect();
+                match metadata.is_empty() {
+                    true => String::new(),
+                    false => serde_json::to_string(&metadata)?,
+                }
+            }
+        })
+    }
+
+    /// Sets the field of `todo` from a cell, an empty cell keeps the default. Cells
+    /// are trimmed, except for the text.
+    fn set(self, todo: &mut Todo, cell: &str) -> Result<(), String> {
+        let value = cell.trim();
+        if value.is_empty() {
+            return Ok(());
+        }
+        let date = || {
+            NaiveDate::parse_from_str(value, DATE_FORMAT)
+                .map_err(|_| format!("\"{}\" is no date like 2026-12-31", value))
+        };
+        let id = || Uuid::parse_str(value).map_err(|_| format!("\"{}\" is no valid id", value));
+
+        match self {
+            Field::Id => todo.id = id()?,
+            Field::Text => todo.text = cell.to_string(),
+            Field::Completed => {
+                todo.completed = match value.to_lowercase().as_str() {
+                    "true" | "yes" | "x" | "1" => true,
+                    "false" | "no" | "0" => false,
+                    _ => return Err(format!("\"{}\" is no completed state", value)),
+                }
+            }
+            Field::Priority => {
+                let mut chars = value.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(c), None) if c.is_ascii_alphabetic() => {
+                        todo.priority = Some(c.to_ascii_uppercase())
+                    }
+                    _ => return Err(format!("\"{}\" is no priority from A to Z", value)),
+                }
+            }
+            Field::Created => todo.created = Some(date()?),
+            Field::CompletedAt => todo.completed_at = Some(date()?),
+            Field::Due => todo.set_due(Some(date()?)),
+            Field::List => todo.list = Some(value.to_string()),
+            Field::Parent => todo.parent = Some(id()?),
+            // Added to the due date, whichever column comes first
+            Field::Metadata => todo.metadata.extend(
+                serde_json::from_str::<BTreeMap<String, String>>(value)
+                    .map_err(|_| format!("\"{}\" is no JSON object of strings", value))?,
+            ),
+        }
+        Ok(())
+    }
+}
+
+/// The `csv` section of the config file.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct CsvConfig {
+    /// The exported columns, in order
+    pub columns: Vec<Field>,
+}
+
+impl Default for CsvConfig {
+    fn default() -> Self {
+        Self {
+            columns: Field::ALL.to_vec(),
+        }
+    }
+}
+
+pub fn write(todos: &[Todo], columns: &[Field]) -> Result<String, ApplicationError> {
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    writer.write_record(columns.iter().map(|field| field.name()))?;
+    for todo in todos {
+        let row = columns
+            .iter()
+            .map(|field| field.value(todo, columns))
+            .collect::<Result<Vec<_>, _>>()?;
+        writer.write_record(row)?;
+    }
+    let content = writer
+        .into_inner()
+        .map_err(|err| ApplicationError(err.to_string()))?;
+    String::from_utf8(content).map_err(|err| ApplicationError(err.to_string()))
+}
+
+pub fn header(content: &str) -> Result<Vec<String>, ApplicationError> {
+    let mut reader = csv::Reader::from_reader(content.as_bytes());
+    Ok(reader.headers()?.iter().map(String::from).collect())
+}
+
+/// Maps every column with the name of a field to it.
+pub fn guess_mapping(header: &[String]) -> Vec<Option<Field>> {
+    header.iter().map(|name| Field::from_name(name)).collect()
+}
+
+/// The result of parsing the rows with a column mapping.
+#[derive(Debug, Default)]
+pub struct Parsed {
+    pub todos: Vec<Todo>,
+    /// Line number and reason of every row which couldn't be parsed
+    pub failed: Vec<(u64, String)>,
+    /// The fields the TODOs were read with, the others keep their defaults
+    pub fields: Vec<Field>,
+}
+
+/// Parses the rows, column `i` going into the field `mapping[i]`.
+pub fn parse(content: &str, mapping: &[Option<Field>]) -> Result<Parsed, ApplicationError> {
+    if !mapping.contains(&Some(Field::Text)) {
+        return Err(ApplicationError("No column is mapped to text".to_string()));
+    }
+
+    let mut reader = csv::ReaderBuilder::new()
+        .flexible(true)
+        .from_reader(content.as_bytes());
+    let mut parsed = Parsed::default();
+    for field in mapping.iter().flatten() {
+        if !parsed.fields.contains(field) {
+            parsed.fields.push(*field);
+        }
+    }
+    for record in reader.records() {
+        let record = match record {
+            Ok(record) => record,
+            Err(err) => {
+                let line = err.position().map_or(0, |position| position.line());
+                parsed.failed.push((line, err.to_string()));
+                continue;
+            }
+        };
+        let line = record.position().map_or(0, |position| position.line());
+
+        let mut todo = Todo::new(String::new());
+        let result = record
+            .iter()
+            .zip(mapping)
+            .filter_map(|(value, field)| field.map(|field| (value, field)))
+            .try_for_each(|(value, field)| {
+                field
+                    .set(&mut todo, value)
+                    .map_err(|err| format!("{}: {}", field.name(), err))
+            });
+        match result {
+            Ok(()) if todo.text.is_empty() => parsed.failed.push((line, "text: empty".to_string())),
+            Ok(()) => parsed.todos.push(todo),
+            Err(err) => parsed.failed.push((line, err)),
+        }
+    }
+    Ok(parsed)
+}
+
+impl From<csv::Error> for ApplicationError {
+    fn from(val: csv::Error) -> Self {
+        Self(val.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let mut first = Todo::new(" with \"quotes\", commas\nand lines ".to_string());
+        first.priority = Some('B');
+        first.created = NaiveDate::from_ymd_opt(2026, 10, 1);
+        first.list = Some("Work".to_string());
+        first.set_due(NaiveDate::from_ymd_opt(2026, 10, 20));
+        first
+            .metadata
+            .insert("estimate".to_string(), "2h".to_string());
+        let mut second = Todo::new("second".to_string());
+        second
+            .metadata
+            .insert(DUE_KEY.to_string(), "someday".to_string());
+        second.completed = true;
+        second.parent = Some(first.id);
+        let todos = vec![first, second];
+
+        let content = write(&todos, &Field::ALL).unwrap();
+        assert!(content.starts_with(
+            "id,text,completed,priority,created,completed_at,due,list,parent,metadata\n"
+        ));
+        assert!(content.contains(",2026-10-20,Work,,\"{\"\"estimate\"\":\"\"2h\"\"}\"\n"));
+        let mapping = guess_mapping(&header(&content).unwrap());
+        let parsed = parse(&content, &mapping).unwrap();
+        assert!(parsed.failed.is_empty());
+        assert_eq!(parsed.todos, todos);
+
+        let content = write(&todos, &[Field::Text, Field::Completed]).unwrap();
+        assert_eq!(content.lines().last().unwrap(), "second,true");
+
+        // Without a due column the due date stays in the metadata
+        let columns = [Field::Metadata, Field::Text];
+        let content = write(&todos, &columns).unwrap();
+        let parsed = parse(&content, &guess_mapping(&header(&content).unwrap())).unwrap();
+        assert_eq!(parsed.todos[0].due(), todos[0].due());
+    }
+
+    #[test]
+    fn test_parse_with_mapping() {
+        let content = "Task,Done,Due,Prio\n\
+                       first,yes,2026-10-01,a\n\
+                       second,maybe,,\n\
+                       third,no,2026-13-01,\n\
+                       ,no,,\n\
+                       fifth\n";
+        let header = header(content).unwrap();
+        assert_eq!(
+            guess_mapping(&header),
+            vec![None, None, Some(Field::Due), None]
+        );
+        assert!(parse(content, &guess_mapping(&header)).is_err());
+
+        let mapping = [
+            Some(Field::Text),
+            Some(Field::Completed),
+            Some(Field::Due),
+            Some(Field::Priority),
+        ];
+        let parsed = parse(content, &mapping).unwrap();
+        let texts: Vec<&str> = parsed.todos.iter().map(|t| t.text.as_str()).collect();
+        assert_eq!(texts, vec!["first", "fifth"]);
+        assert!(parsed.todos[0].completed);
+        assert_eq!(parsed.todos[0].priority, Some('A'));
+        assert_eq!(parsed.todos[0].due(), NaiveDate::from_ymd_opt(2026, 10, 1));
+        assert_eq!(
+            parsed.failed,
+            vec![
+                (3, "completed: \"maybe\" is no completed state".to_string()),
+                (
+                    4,
+                    "due: \"2026-13-01\" is no date like 2026-12-31".to_string()
+                ),
+                (5, "text: empty".to_string()),
+            ]
+        );
+    }
+}
@@ -0,0 +1,209 @@
+//! A self-contained HTML report with inline CSS and a script to filter the TODOs,
+//! to attach to mails or put on a static host. It can't be imported.
+
+use crate::todo::Todo;
+use chrono::NaiveDate;
+use std::collections::HashSet;
+use uuid::Uuid;
+
+const STYLE: &str = "
+body { font-family: sans-serif; margin: 2em auto; max-width: 60em; color: #222; }
+h1 { margin-bottom: 0.2em; }
+.summary { color: #666; }
+.filters { display: flex; gap: 1em; margin: 1.5em 0; }
+.filters input { flex: 1; }
+.filters input, .filters select { padding: 0.4em; font-size: 1em; }
+table { border-collapse: collapse; width: 100%; margin-bottom: 2em; }
+th, td { text-align: left; padding: 0.4em 0.6em; border-bottom: 1px solid #ddd; }
+th { background: #f4f4f4; }
+tr.completed .text { text-decoration: line-through; color: #888; }
+.status { white-space: nowrap; }
+.tag { background: #e8eefc; border-radius: 0.3em; padding: 0 0.3em; margin-right: 0.3em; }
+.overdue { color: #c00; font-weight: bold; }
+";
+
+const SCRIPT: &str = "
+function filter() {
+  const search = document.getElementById('search').value.toLowerCase();
+  const status = document.getElementById('status').value;
+  const list = document.getElementById('list').value;
+  for (const section of document.querySelectorAll('section')) {
+    let visible = 0;
+    for (const row of section.querySelectorAll('tbody tr')) {
+      const show = row.textContent.toLowerCase().includes(search)
+        && (status === '' || row.dataset.status === status)
+        && (list === '' || section.dataset.list === list);
+      row.hidden = !show;
+      if (show) visible++;
+    }
+    section.hidden = visible === 0;
+  }
+}
+for (const id of ['search', 'status', 'list']) {
+  document.getElementById(id).addEventListener('input', filter);
+}
+";
+
+/// Default name of TODOs without a list
+const DEFAULT_LIST: &str = "Default";
+
+pub fn write(todos: &[Todo], today: NaiveDate) -> String {
+    let mut lists: Vec<Option<&String>> = Vec::new();
+    for todo in todos {
+        if !lists.contains(&todo.list.as_ref()) {
+            lists.push(todo.list.as_ref());
+        }
+    }
+    lists.sort_by_key(|list| list.is_some());
+
+    let completed = todos.iter().filter(|todo| todo.completed).count();
+    let mut out = String::new();
+    out.push_str("<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n");
+    out.push_str("<meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n");
+    out.push_str("<title>TODO report</title>\n");
+    out.push_str(&format!("<style>{}</style>\n</head>\n<body>\n", STYLE));
+    out.push_str("<h1>TODO report</h1>\n");
+    out.push_str(&format!(
+        "<p class=\"summary\">{} TODO(s), {} open, {} completed, as of {}</p>\n",
+        todos.len(),
+        todos.len() - completed,
+        completed,
+        today.format("%Y-%m-%d")
+    ));
+
+    out.push_str("<div class=\"filters\">\n");
+    out.push_str("<input id=\"search\" type=\"search\" placeholder=\"Search text and tags\">\n");
+    out.push_str("<select id=\"status\">\n<option value=\"\">All statuses</option>\n");
+    out.push_str("<option value=\"open\">Open</option>\n");
+    out.push_str("<option value=\"completed\">Completed</option>\n</select>\n");
+    out.push_str("<select id=\"list\">\n<option value=\"\">All lists</option>\n");
+    for list in &lists {
+        let name = escape(list.map_or(DEFAULT_LIST, |list| list.as_str()));
+        out.push_str(&format!("<option value=\"{0}\">{0}</option>\n", name));
+    }
+    out.push_str("</select>\n</div>\n");
+
+    for list in lists {
+        let on_list: Vec<&Todo> = todos
+            .iter()
+            .filter(|todo| todo.list.as_ref() == list)
+            .collect();
+        let name = escape(list.map_or(DEFAULT_LIST, |list| list.as_str()));
+        out.push_str(&format!(
+            "<section data-list=\"{0}\">\n<h2>{0}</h2>\n<table>\n",
+            name
+        ));
+        out.push_str("<thead><tr><th>Status</th><th>Priority</th><th>TODO</th>");
+        out.push_str("<th>Tags</th><th>Due</th></tr></thead>\n<tbody>\n");
+        for todo in &on_list {
+            write_row(&mut out, todo, depth(todos, todo), today);
+        }
+        out.push_str("</tbody>\n</table>\n</section>\n");
+    }
+
+    out.push_str(&format!("<script>{}</script>\n</body>\n</html>\n", SCRIPT));
+    out
+}
+
+fn write_row(out: &mut String, todo: &Todo, depth: usize, today: NaiveDate) {
+    let (status, label) = if todo.completed {
+        ("completed", "&#10003; Completed")
+    } else {
+        ("open", "Open")
+    };
+    let tags: String = todo
+        .projects()
+        .into_iter()
+        .map(|tag| format!("+{}", tag))
+        .chain(todo.contexts().into_iter().map(|tag| format!("@{}", tag)))
+        .map(|tag| format!("<span class=\"tag\">{}</span>", escape(&tag)))
+        .collect();
+    let due = match todo.due() {
+        Some(due) if !todo.completed && due < today => {
+            format!("<span class=\"overdue\">{}</span>", due.format("%Y-%m-%d"))
+        }
+        Some(due) => due.format("%Y-%m-%d").to_string(),
+        None => String::new(),
+    };
+
+    out.push_str(&format!(
+        "<tr class=\"{0}\" data-status=\"{0}\"><td class=\"status\">{1}</td><td>{2}</td>\
+         <td class=\"text\" style=\"padding-left: {3}em\">{4}</td><td>{5}</td><td>{6}</td></tr>\n",
+        status,
+        label,
+        todo.priority.map(String::from).unwrap_or_default(),
+        0.6 + 1.5 * depth as f32,
+        escape(&todo.text),
+        tags,
+        due
+    ));
+}
+
+/// How many ancestors on the same list a TODO has.
+fn depth(todos: &[Todo], todo: &Todo) -> usize {
+    let mut seen = HashSet::from([todo.id]);
+    let mut parent: Option<Uuid> = todo.parent;
+    let mut depth = 0;
+    while let Some(current) = parent
+        .and_then(|id| todos.iter().find(|other| other.id == id))
+        .filter(|other| other.list == todo.list && seen.insert(other.id))
+    {
+        depth += 1;
+        parent = current.parent;
+    }
+    depth
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::new();
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write() {
+        let today = NaiveDate::from_ymd_opt(2026, 10, 19).unwrap();
+        let mut first = Todo::new("Call <mom> & \"dad\" +family @phone".to_string());
+        first.list = Some("Home".to_string());
+        first.set_due(NaiveDate::from_ymd_opt(2026, 10, 18));
+        let mut child = Todo::new("child".to_string());
+        child.list = first.list.clone();
+        child.parent = Some(first.id);
+        child.completed = true;
+        child.set_due(NaiveDate::from_ymd_opt(2026, 10, 1));
+        let mut cycle = Todo::new("cycle".to_string());
+        cycle.parent = Some(cycle.id);
+        let todos = vec![first, child, cycle];
+
+        let html = write(&todos, today);
+        assert!(html.contains("3 TODO(s), 2 open, 1 completed, as of 2026-10-19"));
+        assert!(html.contains("Call &lt;mom&gt; &amp; &quot;dad&quot; +family @phone"));
+        assert!(
+            html.contains("<span class=\"tag\">+family</span><span class=\"tag\">@phone</span>")
+        );
+        assert!(html.contains("<span class=\"overdue\">2026-10-18</span>"));
+        // Completed ones are never overdue
+        assert!(html.contains("<td>2026-10-01</td>"));
+        assert!(html.contains("style=\"padding-left: 2.1em\">child"));
+        assert!(html.contains("style=\"padding-left: 0.6em\">cycle"));
+
+        // TODOs without a list come first
+        let default = html.find("<section data-list=\"Default\">").unwrap();
+        let home = html.find("<section data-list=\"Home\">").unwrap();
+        assert!(default < home);
+        assert!(html.contains("<option value=\"Home\">Home</option>"));
+    }
+}
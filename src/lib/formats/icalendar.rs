@@ -0,0 +1,391 @@
+//! iCalendar files ([RFC 5545](https://www.rfc-editor.org/rfc/rfc5545)) of `VTODO`
+//! components, as calendar apps export them.
+//!
+//! `+project` tags become `CATEGORIES`. Calendar apps only know the priorities 1 to
+//! 9, so `A` to `H` keep theirs and lower ones become `I`.
+
+use crate::todo::Todo;
+use chrono::{Local, NaiveDate, NaiveDateTime, Utc};
+use uuid::Uuid;
+
+const PRODID: &str = "-//todo_cmd//EN";
+const LIST_PROPERTY: &str = "X-TODO-CMD-LIST";
+/// Metadata key of an imported UID which is no uuid, to export it again
+const UID_KEY: &str = "uid";
+/// Namespace of the ids made from UIDs which are no uuid
+const UID_NAMESPACE: Uuid = Uuid::from_u128(0x5d1c_7a3e_94b2_4f0e_8a61_2c3b_9e4d_7f18);
+/// Lines are folded after this many bytes
+const LINE_LENGTH: usize = 75;
+
+/// The id of a TODO with `uid`. The same UID always gives the same id, so importing
+/// a file again updates the TODOs.
+fn id_from_uid(uid: &str) -> Uuid {
+    Uuid::parse_str(uid).unwrap_or_else(|_| Uuid::new_v5(&UID_NAMESPACE, uid.as_bytes()))
+}
+
+fn uid(todo: &Todo) -> String {
+    match todo.metadata.get(UID_KEY) {
+        Some(uid) if id_from_uid(uid) == todo.id => uid.clone(),
+        _ => todo.id.to_string(),
+    }
+}
+
+pub fn parse(content: &str) -> Vec<Todo> {
+    let mut todos = Vec::new();
+    let mut todo: Option<Todo> = None;
+    let mut categories: Vec<String> = Vec::new();
+    // Components in the VTODO, like alarms, are skipped
+    let mut nested = 0;
+
+    for line in unfold(content) {
+        let Some((name, params, value)) = parse_property(&line) else {
+            continue;
+        };
+        match name.as_str() {
+            "BEGIN" if todo.is_none() => {
+                if value.eq_ignore_ascii_case("VTODO") {
+                    todo = Some(Todo::new(String::new()));
+                    categories.clear();
+                }
+            }
+            "BEGIN" => nested += 1,
+            "END" if nested > 0 => nested -= 1,
+            "END" => {
+                if let Some(mut todo) = todo.take() {
+                    for category in &categories {
+                        add_project(&mut todo, category);
+                    }
+                    todos.push(todo);
+                }
+            }
+            _ if nested > 0 => {}
+            name => {
+                if let Some(todo) = todo.as_mut() {
+                    parse_todo_property(todo, &mut categories, name, params, value);
+                }
+            }
+        }
+    }
+    todos
+}
+
+fn parse_todo_property(
+    todo: &mut Todo,
+    categories: &mut Vec<String>,
+    name: &str,
+    params: &str,
+    value: &str,
+) {
+    match name {
+        "UID" => {
+            todo.id = id_from_uid(value);
+            if Uuid::parse_str(value).is_err() {
+                todo.metadata.insert(UID_KEY.to_string(), value.to_string());
+            }
+        }
+        "SUMMARY" => todo.text = unescape(value).trim().to_string(),
+        "STATUS" => {
+            todo.completed = matches!(value.to_uppercase().as_str(), "COMPLETED" | "CANCELLED")
+        }
+        "PRIORITY" => {
+            todo.priority = value
+                .trim()
+                .parse::<u8>()
+                .ok()
+                .filter(|priority| (1..=9).contains(priority))
+                .map(|priority| (b'A' + priority - 1) as char)
+        }
+        "DUE" => todo.set_due(parse_date(value)),
+        "CREATED" => todo.created = parse_date(value),
+        "COMPLETED" => todo.completed_at = parse_date(value),
+        "CATEGORIES" => categories.extend(split_list(value)),
+        // Other relations are siblings or children
+        "RELATED-TO" if !params.contains("RELTYPE=") || params.contains("RELTYPE=PARENT") => {
+            todo.parent = Some(id_from_uid(value))
+        }
+        LIST_PROPERTY => todo.list = Some(unescape(value)).filter(|list| !list.is_empty()),
+        _ => {}
+    }
+}
+
+/// Adds `category` to the text as a `+project` tag, unless it is one already.
+fn add_project(todo: &mut Todo, category: &str) {
+    let tag = category.split_whitespace().collect::<Vec<_>>().join("-");
+    if tag.is_empty() || todo.projects().contains(&tag.as_str()) {
+        return;
+    }
+    if !todo.text.is_empty() {
+        todo.text.push(' ');
+    }
+    todo.text.push('+');
+    todo.text.push_str(&tag);
+}
+
+/// The date of a `DATE` or `DATE-TIME` value, the time is dropped. UTC times, ending
+/// with `Z`, are on their local date.
+fn parse_date(value: &str) -> Option<NaiveDate> {
+    if let Ok(time) = NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%SZ") {
+        return Some(time.and_utc().with_timezone(&Local).date_naive());
+    }
+    NaiveDate::parse_from_str(value.get(..8)?, "%Y%m%d").ok()
+}
+
+/// The UTC `DATE-TIME` of the start of a local date, as `CREATED` and `COMPLETED`
+/// can't be dates.
+fn write_date_time(date: NaiveDate) -> String {
+    let midnight = date.and_hms_opt(0, 0, 0).unwrap_or_default();
+    let time = match midnight.and_local_timezone(Local).earliest() {
+        Some(time) => time.with_timezone(&Utc),
+        None => midnight.and_utc(),
+    };
+    time.format("%Y%m%dT%H%M%SZ").to_string()
+}
+
+/// Joins folded lines, which continue with a space or tab.
+fn unfold(content: &str) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    for line in content.lines() {
+        match (line.strip_prefix([' ', '\t']), lines.last_mut()) {
+            (Some(rest), Some(last)) => last.push_str(rest),
+            _ => lines.push(line.to_string()),
+        }
+    }
+    lines
+}
+
+/// Name in upper case, parameters and value of a `NAME;PARAM=x:value` line.
+fn parse_property(line: &str) -> Option<(String, &str, &str)> {
+    // Quoted parameter values may contain colons
+    let mut quoted = false;
+    let colon = line.char_indices().find_map(|(i, c)| match c {
+        '"' => {
+            quoted = !quoted;
+            None
+        }
+        ':' if !quoted => Some(i),
+        _ => None,
+    })?;
+    let (head, value) = (&line[..colon], &line[colon + 1..]);
+    let (name, params) = head.split_once(';').unwrap_or((head, ""));
+    Some((name.to_uppercase(), params, value))
+}
+
+fn unescape(value: &str) -> String {
+    split_escaped(value, None).concat()
+}
+
+/// The values of a comma separated list.
+fn split_list(value: &str) -> Vec<String> {
+    split_escaped(value, Some(','))
+        .into_iter()
+        .map(|item| item.trim().to_string())
+        .filter(|item| !item.is_empty())
+        .collect()
+}
+
+fn split_escaped(value: &str, separator: Option<char>) -> Vec<String> {
+    let mut items = vec![String::new()];
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        let item = items.last_mut().unwrap();
+        match c {
+            '\\' => match chars.next() {
+                Some('n') | Some('N') => item.push('\n'),
+                Some(c) => item.push(c),
+                None => {}
+            },
+            c if Some(c) == separator => items.push(String::new()),
+            c => item.push(c),
+        }
+    }
+    items
+}
+
+fn escape(value: &str) -> String {
+    let mut escaped = String::new();
+    for c in value.chars() {
+        match c {
+            '\\' | ';' | ',' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            '\n' => escaped.push_str("\\n"),
+            '\r' => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Splits a line into lines of at most `LINE_LENGTH` bytes.
+fn fold(line: &str) -> String {
+    let mut folded = String::new();
+    let mut length = 0;
+    for c in line.chars() {
+        if length + c.len_utf8() > LINE_LENGTH {
+            folded.push_str("\r\n ");
+            length = 1;
+        }
+        folded.push(c);
+        length += c.len_utf8();
+    }
+    folded
+}
+
+pub fn write(todos: &[Todo]) -> String {
+    let stamp = Utc::now().format("%Y%m%dT%H%M%SZ").to_string();
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        format!("PRODID:{}", PRODID),
+    ];
+    for todo in todos {
+        lines.extend(write_todo(todo, todos, &stamp));
+    }
+    lines.push("END:VCALENDAR".to_string());
+    lines.iter().map(|line| fold(line) + "\r\n").collect()
+}
+
+fn write_todo(todo: &Todo, todos: &[Todo], stamp: &str) -> Vec<String> {
+    let mut lines = vec![
+        "BEGIN:VTODO".to_string(),
+        format!("UID:{}", uid(todo)),
+        format!("DTSTAMP:{}", stamp),
+        format!("SUMMARY:{}", escape(&todo.text)),
+    ];
+    let status = if todo.completed {
+        "COMPLETED"
+    } else {
+        "NEEDS-ACTION"
+    };
+    lines.push(format!("STATUS:{}", status));
+    // The stored priority may be any character
+    let priority = todo.priority.filter(char::is_ascii_uppercase);
+    if let Some(priority) = priority.and_then(|priority| (priority as u8).checked_sub(b'A')) {
+        lines.push(format!("PRIORITY:{}", (priority + 1).min(9)));
+    }
+    if let Some(due) = todo.due() {
+        lines.push(format!("DUE;VALUE=DATE:{}", due.format("%Y%m%d")));
+    }
+    let projects: Vec<String> = todo.projects().into_iter().map(escape).collect();
+    if !projects.is_empty() {
+        lines.push(format!("CATEGORIES:{}", projects.join(",")));
+    }
+    if let Some(created) = todo.created {
+        lines.push(format!("CREATED:{}", write_date_time(created)));
+    }
+    if let Some(completed_at) = todo.completed_at {
+        lines.push(format!("COMPLETED:{}", write_date_time(completed_at)));
+    }
+    if let Some(parent) = todo.parent {
+        let parent = match todos.iter().find(|other| other.id == parent) {
+            Some(parent) => uid(parent),
+            None => parent.to_string(),
+        };
+        lines.push(format!("RELATED-TO;RELTYPE=PARENT:{}", parent));
+    }
+    if let Some(list) = &todo.list {
+        lines.push(format!("{}:{}", LIST_PROPERTY, escape(list)));
+    }
+    lines.push("END:VTODO".to_string());
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_calendar_export() {
+        let content = "BEGIN:VCALENDAR\r\n\
+                       VERSION:2.0\r\n\
+                       BEGIN:VEVENT\r\n\
+                       UID:event\r\n\
+                       SUMMARY:No TODO\r\n\
+                       END:VEVENT\r\n\
+                       BEGIN:VTODO\r\n\
+                       UID:abc@example.com\r\n\
+                       SUMMARY:Buy milk\\, eggs\r\n\
+                       \x20 and bread\r\n\
+                       CATEGORIES:Shopping,Big list\r\n\
+                       PRIORITY:5\r\n\
+                       DUE;TZID=\"Europe/Berlin:x\":20261020T120000\r\n\
+                       BEGIN:VALARM\r\n\
+                       SUMMARY:Alarm\r\n\
+                       END:VALARM\r\n\
+                       END:VTODO\r\n\
+                       BEGIN:VTODO\r\n\
+                       UID:def@example.com\r\n\
+                       SUMMARY:Cancelled\r\n\
+                       STATUS:CANCELLED\r\n\
+                       RELATED-TO:abc@example.com\r\n\
+                       END:VTODO\r\n\
+                       END:VCALENDAR\r\n";
+        let todos = parse(content);
+        assert_eq!(todos.len(), 2);
+        assert_eq!(
+            todos[0].text,
+            "Buy milk, eggs and bread +Shopping +Big-list"
+        );
+        assert_eq!(todos[0].priority, Some('E'));
+        assert_eq!(todos[0].due(), NaiveDate::from_ymd_opt(2026, 10, 20));
+        assert!(!todos[0].completed);
+        assert!(todos[1].completed);
+        assert_eq!(todos[1].parent, Some(todos[0].id));
+
+        // Importing again gives the same ids, exporting keeps the UIDs
+        assert_eq!(parse(content), todos);
+        let written = write(&todos);
+        assert!(written.contains("\r\nUID:abc@example.com\r\n"));
+        assert!(written.contains("\r\nRELATED-TO;RELTYPE=PARENT:abc@example.com\r\n"));
+        assert_eq!(parse(&written), todos);
+    }
+
+    #[test]
+    fn test_write_priority() {
+        let mut todo = Todo::new("prioritized".to_string());
+        for (priority, written) in [('A', Some(1)), ('Z', Some(9)), ('a', None), ('ä', None)] {
+            todo.priority = Some(priority);
+            let lines = write_todo(&todo, &[], "");
+            let expected = written.map(|written| format!("PRIORITY:{}", written));
+            assert_eq!(
+                lines.into_iter().find(|line| line.starts_with("PRIORITY:")),
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_date() {
+        let date = NaiveDate::from_ymd_opt(2026, 10, 20);
+        assert_eq!(parse_date("20261020"), date);
+        assert_eq!(parse_date("20261020T233000"), date);
+        let utc = NaiveDateTime::parse_from_str("20261020T233000", "%Y%m%dT%H%M%S").unwrap();
+        assert_eq!(
+            parse_date("20261020T233000Z"),
+            Some(utc.and_utc().with_timezone(&Local).date_naive())
+        );
+        assert_eq!(parse_date("2026"), None);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let mut first = Todo::new("Call mom, then; dad\\\n +family".to_string());
+        first.priority = Some('A');
+        first.created = NaiveDate::from_ymd_opt(2026, 10, 1);
+        first.set_due(NaiveDate::from_ymd_opt(2026, 10, 20));
+        first.list = Some("Home".to_string());
+        let mut second = Todo::new("ü".repeat(50));
+        second.completed = true;
+        second.completed_at = NaiveDate::from_ymd_opt(2026, 10, 19);
+        second.parent = Some(first.id);
+        let todos = vec![first, second];
+
+        let written = write(&todos);
+        assert!(written.lines().all(|line| line.len() <= LINE_LENGTH + 1));
+        assert!(written.contains("\r\nCATEGORIES:family\r\n"));
+        assert!(written.contains("\r\nDUE;VALUE=DATE:20261020\r\n"));
+        assert!(written.contains("\r\nPRIORITY:1\r\n"));
+        assert_eq!(parse(&written), todos);
+    }
+}
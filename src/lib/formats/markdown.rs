@@ -0,0 +1,194 @@
+//! GitHub-flavored Markdown task lists. Every list gets a heading, subtasks are
+//! indented below their parent:
+//!
+//! ```markdown
+//! # Home
+//!
+//! - [ ] Clean up
+//!   - [x] Kitchen
+//! ```
+
+use crate::todo::Todo;
+use std::collections::HashSet;
+use uuid::Uuid;
+
+const INDENT: &str = "  ";
+
+pub fn parse(content: &str) -> Vec<Todo> {
+    let mut todos = Vec::new();
+    let mut list = None;
+    // Indentation and id of the items the next ones may be nested in
+    let mut parents: Vec<(usize, Uuid)> = Vec::new();
+
+    for line in content.lines() {
+        let trimmed = line.trim_start();
+        if let Some(heading) = trimmed.strip_prefix('#') {
+            let name = heading.trim_start_matches('#').trim();
+            list = (!name.is_empty()).then(|| name.to_string());
+            parents.clear();
+            continue;
+        }
+        let Some((completed, text)) = parse_item(trimmed) else {
+            continue;
+        };
+
+        let indent = line[..line.len() - trimmed.len()]
+            .chars()
+            .map(|c| if c == '\t' { 4 } else { 1 })
+            .sum();
+        while parents.last().is_some_and(|(level, _)| *level >= indent) {
+            parents.pop();
+        }
+
+        let todo = Todo {
+            id: Uuid::new_v4(),
+            text: text.to_string(),
+            completed,
+            list: list.clone(),
+            parent: parents.last().map(|(_, id)| *id),
+            ..Default::default()
+        };
+        parents.push((indent, todo.id));
+        todos.push(todo);
+    }
+    todos
+}
+
+/// The completed state and text of a bullet, plain bullets are open TODOs.
+fn parse_item(line: &str) -> Option<(bool, &str)> {
+    let rest = ["- ", "* ", "+ "]
+        .iter()
+        .find_map(|bullet| line.strip_prefix(bullet))?;
+    let item = match rest.get(..3) {
+        Some("[ ]") => (false, &rest[3..]),
+        Some("[x]") | Some("[X]") => (true, &rest[3..]),
+        _ => (false, rest),
+    };
+    let text = item.1.trim();
+    (!text.is_empty()).then_some((item.0, text))
+}
+
+pub fn write(todos: &[Todo]) -> String {
+    let mut lists: Vec<Option<&String>> = Vec::new();
+    for todo in todos {
+        if !lists.contains(&todo.list.as_ref()) {
+            lists.push(todo.list.as_ref());
+        }
+    }
+    // TODOs without a list come first, without a heading
+    lists.sort_by_key(|list| list.is_some());
+
+    let mut sections = Vec::new();
+    for list in lists {
+        let on_list: Vec<&Todo> = todos
+            .iter()
+            .filter(|todo| todo.list.as_ref() == list)
+            .collect();
+        let mut section = String::new();
+        if let Some(name) = list {
+            section.push_str(&format!("# {}\n\n", name));
+        }
+
+        let mut written = HashSet::new();
+        let ids: HashSet<Uuid> = on_list.iter().map(|todo| todo.id).collect();
+        for todo in &on_list {
+            // Subtasks of TODOs on other lists are written at the top level
+            if !todo.parent.is_some_and(|parent| ids.contains(&parent)) {
+                write_item(&mut section, &on_list, todo, 0, &mut written);
+            }
+        }
+        // Left over are TODOs in a parent cycle
+        for todo in &on_list {
+            if !written.contains(&todo.id) {
+                write_item(&mut section, &on_list, todo, 0, &mut written);
+            }
+        }
+        sections.push(section);
+    }
+    sections.join("\n")
+}
+
+fn write_item(
+    out: &mut String,
+    todos: &[&Todo],
+    todo: &Todo,
+    depth: usize,
+    written: &mut HashSet<Uuid>,
+) {
+    if !written.insert(todo.id) {
+        return;
+    }
+    let check = if todo.completed { "x" } else { " " };
+    out.push_str(&format!(
+        "{}- [{}] {}\n",
+        INDENT.repeat(depth),
+        check,
+        todo.text
+    ));
+    for child in todos.iter().filter(|child| child.parent == Some(todo.id)) {
+        write_item(out, todos, child, depth + 1, written);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let todos = parse(
+            "Some notes\n\
+             - [ ] no list\n\
+             ## Home\n\n\
+             - [ ] Clean up\n\
+             \x20 - [x] Kitchen\n\
+             \t* plain bullet\n\
+             \x20 - [X] Bath\n\
+             + [ ] Shop\n\
+             1. numbered is no task\n",
+        );
+        let texts: Vec<&str> = todos.iter().map(|todo| todo.text.as_str()).collect();
+        assert_eq!(
+            texts,
+            vec![
+                "no list",
+                "Clean up",
+                "Kitchen",
+                "plain bullet",
+                "Bath",
+                "Shop"
+            ]
+        );
+        assert_eq!(todos[0].list, None);
+        assert!(todos[1..]
+            .iter()
+            .all(|todo| todo.list.as_deref() == Some("Home")));
+        assert!(todos[2].completed && todos[4].completed);
+        assert!(!todos[3].completed);
+
+        assert_eq!(todos[1].parent, None);
+        assert_eq!(todos[2].parent, Some(todos[1].id));
+        assert_eq!(todos[3].parent, Some(todos[2].id));
+        assert_eq!(todos[4].parent, Some(todos[1].id));
+        assert_eq!(todos[5].parent, None);
+    }
+
+    #[test]
+    fn test_write() {
+        let mut todos = parse("# Home\n- [ ] Clean up\n  - [x] Kitchen\n- [ ] Shop\n");
+        let mut other = Todo::new("no list".to_string());
+        other.parent = Some(todos[1].id);
+        todos.push(other);
+        // Moved after its subtask, still written above it
+        todos.swap(0, 1);
+
+        assert_eq!(
+            write(&todos),
+            "- [ ] no list\n\n\
+             # Home\n\n\
+             - [ ] Clean up\n\
+             \x20 - [x] Kitchen\n\
+             - [ ] Shop\n"
+        );
+    }
+}
@@ -0,0 +1,285 @@
+use crate::{errors::ApplicationError, todo::Todo};
+use chrono::Local;
+use csv::Field;
+use std::{collections::HashMap, path::Path};
+use uuid::Uuid;
+
+pub mod csv;
+pub mod html;
+pub mod icalendar;
+pub mod markdown;
+pub mod org;
+pub mod taskwarrior;
+pub mod todotxt;
+
+/// File formats TODOs can be imported from and exported to.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    TodoTxt,
+    Markdown,
+    Csv,
+    ICalendar,
+    Taskwarrior,
+    Org,
+    /// A report, can't be imported
+    Html,
+}
+
+const SUPPORTED: &str = ".txt (todo.txt), .md (Markdown), .csv, .ics (iCalendar), \
+                         .json (Taskwarrior), .org, \
+                         .html (report, export only)";
+
+impl Format {
+    /// The format of a file, by its extension.
+    pub fn from_path(path: &Path) -> Result<Format, ApplicationError> {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("txt") => Ok(Format::TodoTxt),
+            Some("md") | Some("markdown") => Ok(Format::Markdown),
+            Some("csv") => Ok(Format::Csv),
+            Some("ics") => Ok(Format::ICalendar),
+            Some("json") => Ok(Format::Taskwarrior),
+            Some("org") => Ok(Format::Org),
+            Some("html") | Some("htm") => Ok(Format::Html),
+            _ => Err(ApplicationError(format!(
+                "Unknown format of {}, supported are {}",
+                path.display(),
+                SUPPORTED
+            ))),
+        }
+    }
+
+    /// CSV columns are mapped to the fields of the same name, rows that can't be
+    /// parsed are skipped and returned as failed, like the interactive import does.
+    pub fn parse(self, content: &str) -> Result<csv::Parsed, ApplicationError> {
+        let todos = match self {
+            Format::TodoTxt => todotxt::parse(content),
+            Format::Markdown => markdown::parse(content),
+            Format::ICalendar => icalendar::parse(content),
+            Format::Taskwarrior => taskwarrior::parse(content)?,
+            Format::Org => org::parse(content),
+            Format::Html => {
+                return Err(ApplicationError(
+                    "HTML reports can't be imported".to_string(),
+                ))
+            }
+            Format::Csv => {
+                let mapping = csv::guess_mapping(&csv::header(content)?);
+                return csv::parse(content, &mapping);
+            }
+        };
+        Ok(csv::Parsed {
+            todos,
+            failed: Vec::new(),
+            fields: self.fields().to_vec(),
+        })
+    }
+
+    /// The fields a file of the format holds. Those of CSV files depend on their
+    /// columns, these are all of them.
+    pub fn fields(self) -> &'static [Field] {
+        match self {
+            Format::TodoTxt | Format::Csv | Format::Taskwarrior | Format::Org => &Field::ALL,
+            Format::Markdown => &[Field::Text, Field::Completed, Field::List, Field::Parent],
+            Format::ICalendar => &[
+                Field::Id,
+                Field::Text,
+                Field::Completed,
+                Field::Priority,
+                Field::Created,
+                Field::CompletedAt,
+                Field::Due,
+                Field::List,
+                Field::Parent,
+            ],
+            Format::Html => &[],
+        }
+    }
+
+    /// CSV files get all columns.
+    pub fn write(self, todos: &[Todo]) -> Result<String, ApplicationError> {
+        match self {
+            Format::TodoTxt => Ok(todotxt::write(todos)),
+            Format::Markdown => Ok(markdown::write(todos)),
+            Format::Csv => csv::write(todos, &csv::Field::ALL),
+            Format::ICalendar => Ok(icalendar::write(todos)),
+            Format::Taskwarrior => taskwarrior::write(todos),
+            Format::Org => Ok(org::write(todos)),
+            Format::Html => Ok(html::write(todos, Local::now().date_naive())),
+        }
+    }
+}
+
+/// What importing changed in the TODO list.
+#[derive(Debug, Default, PartialEq)]
+pub struct Imported {
+    pub added: usize,
+    pub updated: usize,
+    /// Already in the list and left as they are
+    pub duplicates: usize,
+}
+
+/// Adds `imported` to `todos`.
+///
+/// A TODO with the id of an existing one updates its `fields`, the ones the file
+/// holds, and adds its metadata to the existing one. So importing a file exported
+/// before updates the TODOs, without dropping what the format can't hold. Formats
+/// without ids are matched by list and text instead, only the completed state of
+/// those is taken over.
+pub fn merge_imported(todos: &mut Vec<Todo>, imported: Vec<Todo>, fields: &[Field]) -> Imported {
+    let mut result = Imported::default();
+    // Ids of imported TODOs matched with existing ones, to fix up references
+    let mut matched: HashMap<Uuid, Uuid> = HashMap::new();
+
+    for mut todo in imported {
+        if let Some(parent) = todo.parent.and_then(|parent| matched.get(&parent)) {
+            todo.parent = Some(*parent);
+        }
+
+        if let Some(existing) = todos.iter_mut().find(|existing| existing.id == todo.id) {
+            let updated = update(existing, todo, fields);
+            if *existing != updated {
+                *existing = updated;
+                result.updated += 1;
+            }
+            continue;
+        }
+
+        let duplicate = todos.iter_mut().find(|existing| {
+            existing.text == todo.text
+                && existing.list == todo.list
+                && !matched.values().any(|id| *id == existing.id)
+        });
+        match duplicate {
+            Some(existing) => {
+                matched.insert(todo.id, existing.id);
+                if existing.completed != todo.completed {
+                    existing.completed = todo.completed;
+                    result.updated += 1;
+                } else {
+                    result.duplicates += 1;
+                }
+            }
+            None => {
+                todos.push(todo);
+                result.added += 1;
+            }
+        }
+    }
+    result
+}
+
+/// `existing` with the `fields` and metadata of `todo`.
+fn update(existing: &Todo, todo: Todo, fields: &[Field]) -> Todo {
+    let mut updated = existing.clone();
+    for field in fields {
+        match field {
+            Field::Id | Field::Metadata => {}
+            Field::Text => updated.text = todo.text.clone(),
+            Field::Completed => updated.completed = todo.completed,
+            Field::Priority => updated.priority = todo.priority,
+            Field::Created => updated.created = todo.created,
+            Field::CompletedAt => updated.completed_at = todo.completed_at,
+            Field::Due => updated.set_due(todo.due()),
+            Field::List => updated.list = todo.list.clone(),
+            Field::Parent => updated.parent = todo.parent,
+        }
+    }
+    updated.metadata.extend(todo.metadata);
+    updated
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reimport_detects_duplicates() {
+        let markdown = "# Home\n- [ ] Clean up\n  - [ ] Kitchen\n- [ ] Shop\n";
+        let mut todos = Vec::new();
+        let fields = Format::Markdown.fields();
+        let result = merge_imported(&mut todos, markdown::parse(markdown), fields);
+        assert_eq!(result.added, 3);
+        todos[0].priority = Some('A');
+
+        let changed = "# Home\n- [ ] Clean up\n  - [x] Kitchen\n  - [ ] Bath\n- [ ] Shop\n";
+        let result = merge_imported(&mut todos, markdown::parse(changed), fields);
+        assert_eq!(
+            result,
+            Imported {
+                added: 1,
+                updated: 1,
+                duplicates: 2
+            }
+        );
+        assert_eq!(todos.len(), 4);
+        assert_eq!(todos[0].priority, Some('A'));
+        assert!(todos[1].completed);
+        assert_eq!(todos[3].text, "Bath");
+        assert_eq!(todos[3].parent, Some(todos[0].id));
+
+        // With ids everything is taken over
+        let mut exported = todos.clone();
+        exported[0].priority = None;
+        let imported = todotxt::parse(&todotxt::write(&exported));
+        let result = merge_imported(&mut todos, imported, Format::TodoTxt.fields());
+        assert_eq!(result.updated, 1);
+        assert_eq!(todos, exported);
+    }
+
+    #[test]
+    fn test_reimport_keeps_what_the_format_lacks() {
+        let mut parent = Todo::new("Parent".to_string());
+        parent.created = chrono::NaiveDate::from_ymd_opt(2026, 10, 1);
+        let mut todo = Todo::new("Fix it".to_string());
+        todo.parent = Some(parent.id);
+        todo.metadata
+            .insert("file".to_string(), "/src/main.rs".to_string());
+        todo.metadata.insert("line".to_string(), "12".to_string());
+        let mut todos = vec![parent, todo];
+
+        let mut exported = todos.clone();
+        exported[1].text = "Fix it now".to_string();
+        exported[1].completed = true;
+        exported[1].set_due(chrono::NaiveDate::from_ymd_opt(2026, 10, 20));
+        exported[1].metadata.clear();
+        let content = Format::ICalendar.write(&exported).unwrap();
+        let imported = Format::ICalendar.parse(&content).unwrap();
+        let result = merge_imported(&mut todos, imported.todos, &imported.fields);
+        assert_eq!(result.updated, 1);
+        assert_eq!(todos[1].text, "Fix it now");
+        assert!(todos[1].completed);
+        assert_eq!(todos[1].parent, Some(todos[0].id));
+        assert_eq!(todos[0].created, exported[0].created);
+        assert_eq!(todos[1].due(), exported[1].due());
+        assert_eq!(todos[1].metadata["file"], "/src/main.rs");
+        assert_eq!(todos[1].metadata["line"], "12");
+
+        // Fields the format holds are taken over even when they are empty
+        let mut exported = todos.clone();
+        exported[1].parent = None;
+        exported[1]
+            .metadata
+            .insert("line".to_string(), "14".to_string());
+        let content = Format::Taskwarrior.write(&exported).unwrap();
+        let imported = Format::Taskwarrior.parse(&content).unwrap();
+        let result = merge_imported(&mut todos, imported.todos, &imported.fields);
+        assert_eq!(result.updated, 1);
+        assert_eq!(todos[1].parent, None);
+        assert_eq!(todos[1].metadata["line"], "14");
+        assert_eq!(todos[1].metadata["file"], "/src/main.rs");
+    }
+
+    #[test]
+    fn test_parse_skips_failed_rows() {
+        let content = "text,completed,priority\nFirst,false,A\nSecond,maybe,B\nThird,true,\n";
+        let parsed = Format::Csv.parse(content).unwrap();
+        let texts: Vec<&str> = parsed.todos.iter().map(|todo| todo.text.as_str()).collect();
+        assert_eq!(texts, vec!["First", "Third"]);
+        assert_eq!(parsed.failed.len(), 1);
+        assert_eq!(parsed.failed[0].0, 3);
+
+        assert!(Format::Csv.parse("completed\ntrue\n").is_err());
+        assert!(Format::Html.parse("<html>").is_err());
+        assert!(Format::TodoTxt.parse("x done\n").unwrap().failed.is_empty());
+    }
+}
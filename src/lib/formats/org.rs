@@ -0,0 +1,488 @@
+//! Emacs [Org](https://orgmode.org) files. TODOs are headlines with a `TODO` or
+//! `DONE` keyword, subtasks are nested below their parent and lists are top level
+//! headlines without a keyword:
+//!
+//! ```org
+//! * Home
+//! ** TODO [#A] Clean up +house :house:
+//! DEADLINE: <2026-10-20 Tue>
+//! :PROPERTIES:
+//! :ID: 0c3b2f4e-7a4d-4c5e-9f1a-2b3c4d5e6f70
+//! :END:
+//! *** DONE Kitchen
+//! ```
+//!
+//! Tags are the `+tag`s in the text. The deadline is the due date, fields without a
+//! place in the headline and other metadata are properties. Text and list names which
+//! a headline can't hold as they are, e.g. with line breaks or starting with `TODO`,
+//! are escaped in the headline and kept in the `TEXT` and `LIST` properties.
+
+use crate::todo::{Todo, DUE_KEY};
+use chrono::NaiveDate;
+use serde_json::json;
+use std::collections::HashSet;
+use uuid::Uuid;
+
+/// Metadata key of the scheduled date
+const SCHEDULED_KEY: &str = "scheduled";
+/// Org's way to keep words from being read as markup
+const ZERO_WIDTH_SPACE: char = '\u{200B}';
+
+pub fn parse(content: &str) -> Vec<Todo> {
+    let mut todos: Vec<Todo> = Vec::new();
+    let mut list = None;
+    // Level and index of the headlines the next ones may be nested in, `None` for
+    // headlines which are no TODOs
+    let mut parents: Vec<(usize, Option<usize>)> = Vec::new();
+    // Index of the TODO every TODO is nested in. Ids are only known once the
+    // properties are read
+    let mut nested_in: Vec<Option<usize>> = Vec::new();
+    // Whether the lines below the headline belong to the last TODO
+    let mut in_todo = false;
+    let mut in_drawer = false;
+
+    for line in content.lines() {
+        if let Some((level, headline)) = parse_stars(line) {
+            in_drawer = false;
+            while parents.last().is_some_and(|(parent, _)| *parent >= level) {
+                parents.pop();
+            }
+            let Some(mut todo) = parse_headline(headline) else {
+                if level == 1 {
+                    let name = headline.trim();
+                    list = (!name.is_empty()).then(|| name.to_string());
+                }
+                parents.push((level, None));
+                in_todo = false;
+                continue;
+            };
+
+            if level == 1 {
+                list = None;
+            }
+            todo.list = list.clone();
+            nested_in.push(parents.iter().rev().find_map(|(_, index)| *index));
+            parents.push((level, Some(todos.len())));
+            todos.push(todo);
+            in_todo = true;
+            continue;
+        }
+
+        let (Some(todo), true) = (todos.last_mut(), in_todo) else {
+            continue;
+        };
+        let line = line.trim();
+        if line.eq_ignore_ascii_case(":PROPERTIES:") {
+            in_drawer = true;
+        } else if in_drawer {
+            if line.eq_ignore_ascii_case(":END:") {
+                in_drawer = false;
+            } else {
+                parse_property(todo, line);
+            }
+        } else {
+            parse_planning(todo, line);
+        }
+    }
+
+    for (i, parent) in nested_in.into_iter().enumerate() {
+        if let Some(parent) = parent.filter(|_| todos[i].parent.is_none()) {
+            todos[i].parent = Some(todos[parent].id);
+        }
+    }
+    todos
+}
+
+/// The level and the rest of a `** headline`.
+fn parse_stars(line: &str) -> Option<(usize, &str)> {
+    let rest = line.trim_start_matches('*');
+    let level = line.len() - rest.len();
+    let valid = level > 0 && (rest.is_empty() || rest.starts_with(' '));
+    valid.then_some((level, rest))
+}
+
+/// The TODO of a headline, `None` if it has no TODO keyword.
+fn parse_headline(headline: &str) -> Option<Todo> {
+    let mut words: Vec<&str> = headline.split_whitespace().collect();
+    let completed = match words.first() {
+        Some(&"TODO") => false,
+        Some(&"DONE") => true,
+        _ => return None,
+    };
+    words.remove(0);
+
+    let mut todo = Todo::new(String::new());
+    todo.completed = completed;
+    let priority = words.first().and_then(|word| parse_priority(word));
+    if priority.is_some() {
+        words.remove(0);
+        todo.priority = priority;
+    }
+
+    let tags = words
+        .last()
+        .filter(|word| is_tags(word))
+        .map(|word| word.trim_matches(':').split(':').collect::<Vec<_>>());
+    if tags.is_some() {
+        words.pop();
+    }
+    todo.text = words.join(" ");
+    for tag in tags.into_iter().flatten().filter(|tag| !tag.is_empty()) {
+        if !todo.projects().contains(&tag) {
+            todo.text = format!("{} +{}", todo.text, tag).trim_start().to_string();
+        }
+    }
+    Some(todo)
+}
+
+/// The priority of a `[#A]` cookie.
+fn parse_priority(word: &str) -> Option<char> {
+    let priority = word.strip_prefix("[#")?.strip_suffix(']')?;
+    let mut chars = priority.chars();
+    let first = chars.next().filter(char::is_ascii_uppercase)?;
+    chars.next().is_none().then_some(first)
+}
+
+/// Whether the word is the `:tags:` at the end of a headline.
+fn is_tags(word: &str) -> bool {
+    word.len() > 2 && word.starts_with(':') && word.ends_with(':')
+}
+
+/// The date of an `<2026-10-20 Tue>` or `[2026-10-20 Tue 10:00]` timestamp at the
+/// start of `value`.
+fn parse_timestamp(value: &str) -> Option<NaiveDate> {
+    let date = value
+        .strip_prefix('<')
+        .or_else(|| value.strip_prefix('['))?;
+    NaiveDate::parse_from_str(date.get(..10)?, "%Y-%m-%d").ok()
+}
+
+fn write_timestamp(date: NaiveDate, active: bool) -> String {
+    let date = date.format("%Y-%m-%d %a");
+    if active {
+        format!("<{}>", date)
+    } else {
+        format!("[{}]", date)
+    }
+}
+
+/// Reads a `CLOSED: [...] DEADLINE: <...> SCHEDULED: <...>` line.
+fn parse_planning(todo: &mut Todo, line: &str) {
+    const KEYWORDS: [&str; 3] = ["CLOSED:", "DEADLINE:", "SCHEDULED:"];
+    if !KEYWORDS.iter().any(|keyword| line.starts_with(keyword)) {
+        return;
+    }
+    let date = |keyword: &str| {
+        let start = line.find(keyword)? + keyword.len();
+        parse_timestamp(line[start..].trim_start())
+    };
+    if let Some(closed) = date("CLOSED:") {
+        todo.completed_at = Some(closed);
+    }
+    if let Some(deadline) = date("DEADLINE:") {
+        todo.set_due(Some(deadline));
+    }
+    if let Some(scheduled) = date("SCHEDULED:") {
+        let scheduled = scheduled.format("%Y-%m-%d").to_string();
+        todo.metadata.insert(SCHEDULED_KEY.to_string(), scheduled);
+    }
+}
+
+/// Reads a `:KEY: value` line of a property drawer.
+fn parse_property(todo: &mut Todo, line: &str) {
+    let Some((key, value)) = line.strip_prefix(':').and_then(|line| line.split_once(':')) else {
+        return;
+    };
+    let value = value.trim();
+    match key.to_uppercase().as_str() {
+        "ID" => {
+            if let Ok(id) = Uuid::parse_str(value) {
+                todo.id = id;
+            }
+        }
+        "CREATED" => todo.created = parse_timestamp(value),
+        "PARENT" => todo.parent = Uuid::parse_str(value).ok(),
+        // What the headlines can't hold, as JSON strings
+        "TEXT" if serde_json::from_str::<String>(value).is_ok() => {
+            todo.text = serde_json::from_str(value).unwrap_or_default();
+        }
+        "LIST" if serde_json::from_str::<String>(value).is_ok() => {
+            todo.list = serde_json::from_str(value).ok();
+        }
+        _ if key.is_empty() => {}
+        _ => {
+            todo.metadata.insert(key.to_string(), value.to_string());
+        }
+    }
+}
+
+pub fn write(todos: &[Todo]) -> String {
+    let mut lists: Vec<Option<&String>> = Vec::new();
+    for todo in todos {
+        if !lists.contains(&todo.list.as_ref()) {
+            lists.push(todo.list.as_ref());
+        }
+    }
+    // TODOs without a list come first, at the top level
+    lists.sort_by_key(|list| list.is_some());
+
+    let mut out = String::new();
+    for list in lists {
+        let on_list: Vec<&Todo> = todos
+            .iter()
+            .filter(|todo| todo.list.as_ref() == list)
+            .collect();
+        let level = match list {
+            Some(name) => {
+                out.push_str(&format!("* {}\n", headline_text(name)));
+                2
+            }
+            None => 1,
+        };
+
+        let mut written = HashSet::new();
+        let ids: HashSet<Uuid> = on_list.iter().map(|todo| todo.id).collect();
+        for todo in &on_list {
+            if !todo.parent.is_some_and(|parent| ids.contains(&parent)) {
+                write_item(&mut out, &on_list, todo, level, false, &mut written);
+            }
+        }
+        // Left over are TODOs in a parent cycle
+        for todo in &on_list {
+            if !written.contains(&todo.id) {
+                write_item(&mut out, &on_list, todo, level, false, &mut written);
+            }
+        }
+    }
+    out
+}
+
+fn write_item(
+    out: &mut String,
+    todos: &[&Todo],
+    todo: &Todo,
+    level: usize,
+    nested: bool,
+    written: &mut HashSet<Uuid>,
+) {
+    if !written.insert(todo.id) {
+        return;
+    }
+    write_headline(out, todo, level);
+    // Nested ones have their parent in the headline above
+    write_properties(out, todo, !nested);
+
+    for child in todos.iter().filter(|child| child.parent == Some(todo.id)) {
+        write_item(out, todos, child, level + 1, true, written);
+    }
+}
+
+fn write_headline(out: &mut String, todo: &Todo, level: usize) {
+    let keyword = if todo.completed { "DONE" } else { "TODO" };
+    let mut words = vec!["*".repeat(level), keyword.to_string()];
+    words.extend(todo.priority.map(|priority| format!("[#{}]", priority)));
+    let text = headline_text(&todo.text);
+    if !text.is_empty() {
+        words.push(text);
+    }
+    // Org only allows some characters in tags, others stay in the text only
+    let tags: Vec<&str> = todo
+        .projects()
+        .into_iter()
+        .filter(|tag| {
+            tag.chars()
+                .all(|c| c.is_alphanumeric() || "_@#%".contains(c))
+        })
+        .collect();
+    if !tags.is_empty() {
+        words.push(format!(":{}:", tags.join(":")));
+    }
+    out.push_str(&words.join(" "));
+    out.push('\n');
+
+    let mut planning = Vec::new();
+    if let Some(completed_at) = todo.completed_at {
+        planning.push(format!("CLOSED: {}", write_timestamp(completed_at, false)));
+    }
+    if let Some(due) = todo.due() {
+        planning.push(format!("DEADLINE: {}", write_timestamp(due, true)));
+    }
+    if let Some(scheduled) = scheduled(todo) {
+        planning.push(format!("SCHEDULED: {}", write_timestamp(scheduled, true)));
+    }
+    if !planning.is_empty() {
+        out.push_str(&planning.join(" "));
+        out.push('\n');
+    }
+}
+
+/// `text` on a single line, escaped so it isn't read as the keyword, the priority or
+/// the tags of a headline.
+fn headline_text(text: &str) -> String {
+    let mut words: Vec<String> = text.split_whitespace().map(String::from).collect();
+    if let Some(first) = words.first_mut() {
+        if first == "TODO" || first == "DONE" || parse_priority(first).is_some() {
+            first.insert(0, ZERO_WIDTH_SPACE);
+        }
+    }
+    if let Some(last) = words.last_mut().filter(|last| is_tags(last)) {
+        last.push(ZERO_WIDTH_SPACE);
+    }
+    words.join(" ")
+}
+
+fn scheduled(todo: &Todo) -> Option<NaiveDate> {
+    let scheduled = todo.metadata.get(SCHEDULED_KEY)?;
+    NaiveDate::parse_from_str(scheduled, "%Y-%m-%d").ok()
+}
+
+fn write_properties(out: &mut String, todo: &Todo, with_parent: bool) {
+    out.push_str(":PROPERTIES:\n");
+    out.push_str(&format!(":ID: {}\n", todo.id));
+    if let Some(created) = todo.created {
+        out.push_str(&format!(":CREATED: {}\n", write_timestamp(created, false)));
+    }
+    if let Some(parent) = todo.parent.filter(|_| with_parent) {
+        out.push_str(&format!(":PARENT: {}\n", parent));
+    }
+    if headline_text(&todo.text) != todo.text {
+        out.push_str(&format!(":TEXT: {}\n", json!(todo.text)));
+    }
+    if let Some(list) = todo
+        .list
+        .as_ref()
+        .filter(|list| headline_text(list) != **list)
+    {
+        out.push_str(&format!(":LIST: {}\n", json!(list)));
+    }
+    for (key, value) in &todo.metadata {
+        // Valid dates are in the planning line
+        let planned = (key == DUE_KEY && todo.due().is_some())
+            || (key == SCHEDULED_KEY && scheduled(todo).is_some());
+        if !planned {
+            out.push_str(&format!(":{}: {}\n", key, value));
+        }
+    }
+    out.push_str(":END:\n");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let todos = parse(
+            "#+TITLE: Notes\n\
+             * TODO No list\n\
+             * Home\n\
+             Some notes\n\
+             ** TODO [#A] Clean up :house:chores:\n\
+             DEADLINE: <2026-10-20 Tue> SCHEDULED: <2026-10-18 Sun>\n\
+             :PROPERTIES:\n\
+             :CREATED: [2026-10-01 Thu]\n\
+             :Effort: 1:00\n\
+             :END:\n\
+             *** Plain headline\n\
+             **** DONE Kitchen +house :house:\n\
+             CLOSED: [2026-10-19 Mon 10:00]\n\
+             ** Not a TODO\n\
+             *** TODO Shop\n\
+             *bold* is no headline\n",
+        );
+        let texts: Vec<&str> = todos.iter().map(|todo| todo.text.as_str()).collect();
+        assert_eq!(
+            texts,
+            vec![
+                "No list",
+                "Clean up +house +chores",
+                "Kitchen +house",
+                "Shop"
+            ]
+        );
+        assert_eq!(todos[0].list, None);
+        assert!(todos[1..]
+            .iter()
+            .all(|todo| todo.list.as_deref() == Some("Home")));
+
+        let clean_up = &todos[1];
+        assert_eq!(clean_up.priority, Some('A'));
+        assert_eq!(clean_up.due(), NaiveDate::from_ymd_opt(2026, 10, 20));
+        assert_eq!(clean_up.metadata[SCHEDULED_KEY], "2026-10-18");
+        assert_eq!(clean_up.metadata["Effort"], "1:00");
+        assert_eq!(clean_up.created, NaiveDate::from_ymd_opt(2026, 10, 1));
+
+        assert!(todos[2].completed);
+        assert_eq!(todos[2].completed_at, NaiveDate::from_ymd_opt(2026, 10, 19));
+        assert_eq!(todos[2].parent, Some(clean_up.id));
+        assert_eq!(todos[3].parent, None);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let mut todos = parse(
+            "* Home\n\
+             ** TODO [#A] Clean up +house\n\
+             DEADLINE: <2026-10-20 Tue> SCHEDULED: <2026-10-18 Sun>\n\
+             *** DONE Kitchen\n\
+             CLOSED: [2026-10-19 Mon]\n",
+        );
+        todos[0]
+            .metadata
+            .insert("due".to_string(), "soon".to_string());
+        let mut other = Todo::new("no list +some-tag".to_string());
+        other.parent = Some(todos[1].id);
+        other.created = NaiveDate::from_ymd_opt(2026, 10, 1);
+        todos.push(other);
+        // Moved after its subtask, still written above it
+        todos.swap(0, 1);
+
+        let written = write(&todos);
+        assert!(written.starts_with(&format!(
+            "* TODO no list +some-tag\n\
+             :PROPERTIES:\n\
+             :ID: {}\n\
+             :CREATED: [2026-10-01 Thu]\n\
+             :PARENT: {}\n\
+             :END:\n\
+             * Home\n\
+             ** TODO [#A] Clean up +house :house:\n\
+             SCHEDULED: <2026-10-18 Sun>\n",
+            todos[2].id, todos[0].id
+        )));
+
+        let mut parsed = parse(&written);
+        parsed.sort_by_key(|todo| todo.id);
+        todos.sort_by_key(|todo| todo.id);
+        assert_eq!(parsed, todos);
+    }
+
+    #[test]
+    fn test_round_trip_text() {
+        let mut todos: Vec<Todo> = [
+            "TODO later",
+            "DONE deal",
+            "[#B] is no priority",
+            "ends in :tag:",
+            "Two\nlines",
+            "  spaced  out ",
+            "plain +tag",
+        ]
+        .iter()
+        .map(|text| Todo::new(text.to_string()))
+        .collect();
+        todos[0].list = Some("TODO x".to_string());
+        todos[1].list = Some("Two\nlines".to_string());
+        todos[2].completed = true;
+
+        let written = write(&todos);
+        assert!(written.contains("\n** TODO \u{200B}TODO later\n"));
+        assert!(written.contains("\n* \u{200B}TODO x\n"));
+        assert!(written.contains("\n:TEXT: \"Two\\nlines\"\n"));
+        assert_eq!(written.matches(":TEXT:").count(), 6);
+        let mut parsed = parse(&written);
+        parsed.sort_by_key(|todo| todo.id);
+        todos.sort_by_key(|todo| todo.id);
+        assert_eq!(parsed, todos);
+    }
+}
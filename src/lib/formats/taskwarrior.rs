@@ -0,0 +1,296 @@
+//! [Taskwarrior](https://taskwarrior.org)'s JSON, as `task export` writes and
+//! `task import` reads it.
+//!
+//! Tags are the `+tag`s in the text, exported without them in the description, and
+//! the project is the list. Taskwarrior only has the priorities `H`, `M` and `L`,
+//! which are `A`, `B` and `C` here, lower ones are exported as `L`. Attributes
+//! without a field, like `wait`, `depends` or user defined ones, are kept in the
+//! metadata, those which aren't strings as their JSON.
+
+use crate::{errors::ApplicationError, todo::Todo};
+use chrono::{Local, NaiveDate, NaiveDateTime, Utc};
+use serde_json::{json, Map, Value};
+use uuid::Uuid;
+
+const DATE_TIME_FORMAT: &str = "%Y%m%dT%H%M%SZ";
+/// Metadata key prefix of annotations, followed by the time they were made
+const ANNOTATION_PREFIX: &str = "annotation.";
+/// Metadata key prefix of attributes which aren't strings, like `depends` or numeric
+/// user defined ones, their value is the JSON
+const JSON_PREFIX: &str = "json.";
+/// Metadata key of a status which isn't `pending` or `completed`
+const STATUS_KEY: &str = "status";
+/// Attributes with a field or computed by Taskwarrior, not kept in the metadata
+const KNOWN: [&str; 13] = [
+    "id",
+    "uuid",
+    "description",
+    "status",
+    "entry",
+    "end",
+    "due",
+    "priority",
+    "project",
+    "tags",
+    "annotations",
+    "parent",
+    "urgency",
+];
+
+pub fn parse(content: &str) -> Result<Vec<Todo>, ApplicationError> {
+    // Older versions write a task per line instead of an array
+    let tasks: Vec<Map<String, Value>> = if content.trim_start().starts_with('[') {
+        serde_json::from_str(content)?
+    } else {
+        content
+            .lines()
+            .map(|line| line.trim().trim_end_matches(','))
+            .filter(|line| !line.is_empty())
+            .map(serde_json::from_str)
+            .collect::<Result<_, _>>()?
+    };
+    Ok(tasks.into_iter().map(parse_task).collect())
+}
+
+/// The local date of a UTC time stamp.
+fn parse_date(value: &str) -> Option<NaiveDate> {
+    let time = NaiveDateTime::parse_from_str(value, DATE_TIME_FORMAT).ok()?;
+    Some(time.and_utc().with_timezone(&Local).date_naive())
+}
+
+/// The UTC time stamp of the start of a local date.
+fn write_date(date: NaiveDate) -> String {
+    let midnight = date.and_hms_opt(0, 0, 0).unwrap_or_default();
+    let time = match midnight.and_local_timezone(Local).earliest() {
+        Some(time) => time.with_timezone(&Utc),
+        None => midnight.and_utc(),
+    };
+    time.format(DATE_TIME_FORMAT).to_string()
+}
+
+fn parse_task(task: Map<String, Value>) -> Todo {
+    let string = |key: &str| task.get(key).and_then(Value::as_str);
+    let mut todo = Todo::new(string("description").unwrap_or_default().to_string());
+
+    if let Some(id) = string("uuid").and_then(|uuid| Uuid::parse_str(uuid).ok()) {
+        todo.id = id;
+    }
+    match string("status") {
+        Some("completed") => todo.completed = true,
+        Some("pending") | None => {}
+        Some(status) => {
+            todo.completed = status == "deleted";
+            todo.metadata
+                .insert(STATUS_KEY.to_string(), status.to_string());
+        }
+    }
+    todo.created = string("entry").and_then(parse_date);
+    todo.completed_at = string("end").and_then(parse_date);
+    todo.set_due(string("due").and_then(parse_date));
+    todo.priority = match string("priority") {
+        Some("H") => Some('A'),
+        Some("M") => Some('B'),
+        Some("L") => Some('C'),
+        _ => None,
+    };
+    todo.list = string("project").map(String::from);
+    todo.parent = string("parent").and_then(|parent| Uuid::parse_str(parent).ok());
+
+    let tags = task.get("tags").and_then(Value::as_array);
+    for tag in tags.into_iter().flatten().filter_map(Value::as_str) {
+        if !todo.projects().contains(&tag) {
+            todo.text = format!("{} +{}", todo.text, tag).trim_start().to_string();
+        }
+    }
+
+    let annotations = task.get("annotations").and_then(Value::as_array);
+    for annotation in annotations.into_iter().flatten() {
+        let entry = annotation.get("entry").and_then(Value::as_str);
+        let description = annotation.get("description").and_then(Value::as_str);
+        if let (Some(entry), Some(description)) = (entry, description) {
+            todo.metadata.insert(
+                format!("{}{}", ANNOTATION_PREFIX, entry),
+                description.to_string(),
+            );
+        }
+    }
+
+    for (key, value) in task
+        .iter()
+        .filter(|(key, _)| !KNOWN.contains(&key.as_str()))
+    {
+        match value {
+            Value::String(value) => todo.metadata.insert(key.clone(), value.clone()),
+            value => todo
+                .metadata
+                .insert(format!("{}{}", JSON_PREFIX, key), value.to_string()),
+        };
+    }
+    todo
+}
+
+pub fn write(todos: &[Todo]) -> Result<String, ApplicationError> {
+    let tasks = todos
+        .iter()
+        .map(|todo| serde_json::to_string(&write_task(todo)))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(format!("[\n{}\n]\n", tasks.join(",\n")))
+}
+
+fn write_task(todo: &Todo) -> Map<String, Value> {
+    let mut task = Map::new();
+    task.insert("uuid".to_string(), json!(todo.id));
+    task.insert("description".to_string(), json!(description(&todo.text)));
+    // Taskwarrior's deleted TODOs are completed ones here
+    let status = match todo.metadata.get(STATUS_KEY) {
+        Some(status) if (status == "deleted") == todo.completed => status,
+        _ if todo.completed => "completed",
+        _ => "pending",
+    };
+    task.insert("status".to_string(), json!(status));
+
+    if let Some(created) = todo.created {
+        task.insert("entry".to_string(), json!(write_date(created)));
+    }
+    if let Some(completed_at) = todo.completed_at {
+        task.insert("end".to_string(), json!(write_date(completed_at)));
+    }
+    if let Some(due) = todo.due() {
+        task.insert("due".to_string(), json!(write_date(due)));
+    }
+    if let Some(priority) = todo.priority {
+        let priority = match priority {
+            'A' => "H",
+            'B' => "M",
+            _ => "L",
+        };
+        task.insert("priority".to_string(), json!(priority));
+    }
+    if let Some(list) = &todo.list {
+        task.insert("project".to_string(), json!(list));
+    }
+    if let Some(parent) = todo.parent {
+        task.insert("parent".to_string(), json!(parent));
+    }
+    let tags = todo.projects();
+    if !tags.is_empty() {
+        task.insert("tags".to_string(), json!(tags));
+    }
+
+    let mut annotations = Vec::new();
+    for (key, value) in &todo.metadata {
+        if let Some(entry) = key.strip_prefix(ANNOTATION_PREFIX) {
+            annotations.push(json!({ "entry": entry, "description": value }));
+        } else if let Some(key) = key.strip_prefix(JSON_PREFIX) {
+            // Edited into invalid JSON, it is still better exported as a string
+            let value = serde_json::from_str(value).unwrap_or_else(|_| json!(value));
+            task.insert(key.to_string(), value);
+        } else if !KNOWN.contains(&key.as_str()) {
+            task.insert(key.clone(), json!(value));
+        }
+    }
+    if !annotations.is_empty() {
+        task.insert("annotations".to_string(), json!(annotations));
+    }
+    task
+}
+
+/// The text without its `+tag`s, which Taskwarrior keeps in `tags`. Imported again,
+/// they are added back at the end.
+fn description(text: &str) -> String {
+    let mut description = String::new();
+    let mut stripped = false;
+    for word in text.split_inclusive(char::is_whitespace) {
+        match word.trim_end().strip_prefix('+') {
+            Some(tag) if !tag.is_empty() => stripped = true,
+            _ => description.push_str(word),
+        }
+    }
+    match stripped {
+        true => description.trim_end().to_string(),
+        false => description,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXPORT: &str = r#"[
+{"id":1,"description":"Call mom","due":"20261020T000000Z","entry":"20261001T120000Z","modified":"20261002T120000Z","priority":"H","project":"Home","status":"pending","tags":["family","phone"],"uuid":"0c3b2f4e-7a4d-4c5e-9f1a-2b3c4d5e6f70","annotations":[{"entry":"20261002T120000Z","description":"Ask about the party"}],"urgency":8.9,"estimate":3},
+{"id":0,"description":"Old +task","depends":["0c3b2f4e-7a4d-4c5e-9f1a-2b3c4d5e6f70"],"end":"20261003T120000Z","entry":"20261001T120000Z","status":"deleted","tags":["task"],"uuid":"1c3b2f4e-7a4d-4c5e-9f1a-2b3c4d5e6f70"}
+]"#;
+
+    #[test]
+    fn test_parse() {
+        let todos = parse(EXPORT).unwrap();
+        assert_eq!(todos.len(), 2);
+        let first = &todos[0];
+        assert_eq!(first.text, "Call mom +family +phone");
+        assert_eq!(first.id.to_string(), "0c3b2f4e-7a4d-4c5e-9f1a-2b3c4d5e6f70");
+        assert!(!first.completed);
+        assert_eq!(first.priority, Some('A'));
+        assert_eq!(first.list.as_deref(), Some("Home"));
+        assert_eq!(first.due(), parse_date("20261020T000000Z"));
+        assert_eq!(
+            first.metadata["annotation.20261002T120000Z"],
+            "Ask about the party"
+        );
+        assert_eq!(first.metadata["modified"], "20261002T120000Z");
+        assert_eq!(first.metadata["json.estimate"], "3");
+        assert!(!first.metadata.contains_key("urgency"));
+
+        assert!(todos[1].completed);
+        assert_eq!(todos[1].text, "Old +task");
+        assert_eq!(todos[1].metadata[STATUS_KEY], "deleted");
+        assert_eq!(
+            todos[1].metadata["json.depends"],
+            "[\"0c3b2f4e-7a4d-4c5e-9f1a-2b3c4d5e6f70\"]"
+        );
+
+        // A task per line, as older versions export it
+        let lines = EXPORT.trim_start_matches('[').trim_end_matches(']');
+        assert_eq!(parse(lines).unwrap(), todos);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let mut todos = parse(EXPORT).unwrap();
+        let mut child = Todo::new("child".to_string());
+        child.parent = Some(todos[0].id);
+        child.completed = true;
+        child.created = NaiveDate::from_ymd_opt(2026, 10, 1);
+        child.completed_at = NaiveDate::from_ymd_opt(2026, 10, 19);
+        todos.push(child);
+
+        let written = write(&todos).unwrap();
+        assert_eq!(parse(&written).unwrap(), todos);
+
+        let tasks: Vec<Map<String, Value>> = serde_json::from_str(&written).unwrap();
+        assert_eq!(tasks[0]["description"], "Call mom");
+        assert_eq!(tasks[0]["tags"], json!(["family", "phone"]));
+        assert_eq!(tasks[0]["estimate"], json!(3));
+        assert_eq!(tasks[0]["modified"], "20261002T120000Z");
+        assert_eq!(tasks[1]["description"], "Old");
+        assert_eq!(tasks[1]["depends"], json!([todos[0].id]));
+        assert_eq!(tasks[0]["priority"], "H");
+        assert_eq!(
+            tasks[0]["annotations"][0]["description"],
+            "Ask about the party"
+        );
+        assert_eq!(tasks[1]["status"], "deleted");
+        assert_eq!(tasks[2]["status"], "completed");
+        assert_eq!(tasks[2]["parent"], json!(todos[0].id));
+
+        // Another round trip, as through Taskwarrior, changes nothing
+        assert_eq!(write(&parse(&written).unwrap()).unwrap(), written);
+    }
+
+    #[test]
+    fn test_description() {
+        assert_eq!(description("Call mom +family +phone"), "Call mom");
+        assert_eq!(description("Buy +milk  today\n+x"), "Buy  today");
+        assert_eq!(description("Sum 1 + 2 +"), "Sum 1 + 2 +");
+        assert_eq!(description("Keep  spaces "), "Keep  spaces ");
+    }
+}
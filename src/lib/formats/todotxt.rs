@@ -0,0 +1,344 @@
+//! The [todo.txt](https://github.com/todotxt/todo.txt) format, one TODO per line:
+//! `x (A) 2026-10-19 2026-10-01 text with +project @context key:value`.
+//!
+//! Text which would read back differently, like text starting with `x` or a date,
+//! with words like `10:30` or with line breaks, is written percent-encoded as
+//! `text:...` instead. Metadata keys and values are percent-encoded as well.
+
+use crate::todo::Todo;
+use chrono::NaiveDate;
+use uuid::Uuid;
+
+const DATE_FORMAT: &str = "%Y-%m-%d";
+/// Keys of the fields written as `key:value`, metadata keys of the same name are
+/// written with their first character percent-encoded
+const KNOWN_KEYS: [&str; 6] = ["id", "pri", "created", "list", "text", "parent"];
+
+pub fn parse(content: &str) -> Vec<Todo> {
+    content
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(parse_line)
+        .collect()
+}
+
+fn parse_date(word: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(word, DATE_FORMAT).ok()
+}
+
+fn parse_priority(word: &str) -> Option<char> {
+    let priority = word.strip_prefix('(')?.strip_suffix(')')?;
+    let mut chars = priority.chars();
+    let first = chars.next().filter(char::is_ascii_uppercase)?;
+    chars.next().is_none().then_some(first)
+}
+
+/// `key:value` with neither containing a colon. URLs like `https://...` are text.
+fn parse_key_value(word: &str) -> Option<(&str, &str)> {
+    let (key, value) = word.split_once(':')?;
+    let valid =
+        !key.is_empty() && !value.is_empty() && !value.contains(':') && !value.starts_with("//");
+    valid.then_some((key, value))
+}
+
+fn parse_line(line: &str) -> Todo {
+    let mut todo = Todo {
+        id: Uuid::new_v4(),
+        ..Default::default()
+    };
+    let mut words = line.split_whitespace().peekable();
+
+    if words.peek() == Some(&"x") {
+        words.next();
+        todo.completed = true;
+    }
+    if let Some(priority) = words.peek().and_then(|word| parse_priority(word)) {
+        words.next();
+        todo.priority = Some(priority);
+    }
+    // A completed TODO has the completion date first, then the creation date
+    let first_date = words.peek().and_then(|word| parse_date(word));
+    if first_date.is_some() {
+        words.next();
+        let second_date = words.peek().and_then(|word| parse_date(word));
+        if todo.completed {
+            todo.completed_at = first_date;
+            if second_date.is_some() {
+                words.next();
+                todo.created = second_date;
+            }
+        } else {
+            todo.created = first_date;
+        }
+    }
+
+    let mut text = Vec::new();
+    for word in words {
+        match parse_key_value(word) {
+            Some((key, value)) => {
+                if !parse_known_key(&mut todo, key, value) {
+                    todo.metadata.insert(decode(key), decode(value));
+                }
+            }
+            None => text.push(word),
+        }
+    }
+    // Unless it was written as `text:...`
+    if todo.text.is_empty() {
+        todo.text = text.join(" ");
+    }
+    todo
+}
+
+/// Sets the field stored in the `key:value` pair, `false` if it is none.
+fn parse_known_key(todo: &mut Todo, key: &str, value: &str) -> bool {
+    match key {
+        "id" => Uuid::parse_str(value).map(|id| todo.id = id).is_ok(),
+        // Completed TODOs keep their priority as `pri:A`
+        "pri" => parse_priority(&format!("({})", value))
+            .map(|priority| todo.priority = Some(priority))
+            .is_some(),
+        "created" => parse_date(value)
+            .map(|date| todo.created = Some(date))
+            .is_some(),
+        "list" => {
+            todo.list = Some(decode(value));
+            true
+        }
+        "text" => {
+            todo.text = decode(value);
+            true
+        }
+        "parent" => Uuid::parse_str(value)
+            .map(|id| todo.parent = Some(id))
+            .is_ok(),
+        _ => false,
+    }
+}
+
+pub fn write(todos: &[Todo]) -> String {
+    todos.iter().map(|todo| write_line(todo) + "\n").collect()
+}
+
+fn write_line(todo: &Todo) -> String {
+    let mut words = Vec::new();
+    let mut keys = Vec::new();
+
+    if todo.completed {
+        words.push("x".to_string());
+        if let Some(priority) = todo.priority {
+            keys.push(format!("pri:{}", priority));
+        }
+        match (todo.completed_at, todo.created) {
+            (Some(completed_at), created) => {
+                words.push(completed_at.format(DATE_FORMAT).to_string());
+                words.extend(created.map(|date| date.format(DATE_FORMAT).to_string()));
+            }
+            // A single date would be read as the completion date
+            (None, Some(created)) => keys.push(format!("created:{}", created.format(DATE_FORMAT))),
+            (None, None) => {}
+        }
+    } else {
+        words.extend(todo.priority.map(|priority| format!("({})", priority)));
+        words.extend(
+            todo.created
+                .map(|date| date.format(DATE_FORMAT).to_string()),
+        );
+    }
+
+    if is_plain(&todo.text) {
+        words.extend((!todo.text.is_empty()).then(|| todo.text.clone()));
+    } else {
+        keys.push(format!("text:{}", encode(&todo.text)));
+    }
+    words.extend(keys);
+    words.extend(
+        todo.list
+            .as_ref()
+            .map(|list| format!("list:{}", encode(list))),
+    );
+    words.extend(todo.parent.map(|parent| format!("parent:{}", parent)));
+    words.extend(
+        todo.metadata
+            .iter()
+            .map(|(key, value)| write_metadata(key, value)),
+    );
+    words.push(format!("id:{}", todo.id));
+    words.join(" ")
+}
+
+/// The `key:value` pair of a metadata entry, encoded so that it is read back as
+/// the same entry and not as text or a field.
+fn write_metadata(key: &str, value: &str) -> String {
+    let mut key = encode(key);
+    if KNOWN_KEYS.contains(&key.as_str()) {
+        key = format!("%{:02X}{}", key.as_bytes()[0], &key[1..]);
+    }
+    let mut value = encode(value);
+    // Values starting with `//` are read as part of a URL
+    if let Some(rest) = value.strip_prefix('/') {
+        value = format!("%2F{}", rest);
+    }
+    format!("{}:{}", key, value)
+}
+
+/// Whether `text` is read back the same when written as it is: words separated by
+/// single spaces, none of them a `key:value` pair, and the first one neither `x`, a
+/// priority nor a date.
+fn is_plain(text: &str) -> bool {
+    let first = text.split(' ').next().unwrap_or_default();
+    text.split_whitespace().collect::<Vec<_>>().join(" ") == text
+        && first != "x"
+        && parse_priority(first).is_none()
+        && parse_date(first).is_none()
+        && text.split(' ').all(|word| parse_key_value(word).is_none())
+}
+
+/// Percent-encodes what can't be part of a value, list names may contain spaces.
+fn encode(value: &str) -> String {
+    let mut encoded = String::new();
+    for c in value.chars() {
+        if c == '%' || c == ':' || c.is_whitespace() {
+            let mut bytes = [0; 4];
+            for byte in c.encode_utf8(&mut bytes).bytes() {
+                encoded.push_str(&format!("%{:02X}", byte));
+            }
+        } else {
+            encoded.push(c);
+        }
+    }
+    encoded
+}
+
+fn decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        let code = bytes
+            .get(i + 1..i + 3)
+            .filter(|_| bytes[i] == b'%')
+            .and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok());
+        match code {
+            Some(code) => {
+                decoded.push(code);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let todos = parse(
+            "(A) 2026-10-01 Call mom +family @phone due:2026-10-20\n\n\
+             x 2026-10-19 2026-10-02 Read https://example.com pri:B\n\
+             x not a date\n\
+             (a) lowercase is text\n",
+        );
+        assert_eq!(todos.len(), 4);
+
+        assert_eq!(todos[0].priority, Some('A'));
+        assert_eq!(todos[0].created, parse_date("2026-10-01"));
+        assert_eq!(todos[0].text, "Call mom +family @phone");
+        assert_eq!(todos[0].projects(), vec!["family"]);
+        assert_eq!(todos[0].contexts(), vec!["phone"]);
+        assert_eq!(todos[0].metadata["due"], "2026-10-20");
+
+        assert!(todos[1].completed);
+        assert_eq!(todos[1].completed_at, parse_date("2026-10-19"));
+        assert_eq!(todos[1].created, parse_date("2026-10-02"));
+        assert_eq!(todos[1].priority, Some('B'));
+        assert_eq!(todos[1].text, "Read https://example.com");
+
+        assert!(todos[2].completed);
+        assert_eq!(todos[2].text, "not a date");
+        assert_eq!(todos[3].priority, None);
+        assert_eq!(todos[3].text, "(a) lowercase is text");
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let mut todos = parse(
+            "(A) 2026-10-01 Call mom +family @phone due:2026-10-20\n\
+             x 2026-10-19 2026-10-02 Read pri:B\n",
+        );
+        todos[1].list = Some("Home 100%:\u{A0}today".to_string());
+        todos[1].parent = Some(todos[0].id);
+        let mut created_only = Todo::new("created only".to_string());
+        created_only.completed = true;
+        created_only.created = parse_date("2026-10-03");
+        todos.push(created_only);
+        todos.push(Todo::new("plain".to_string()));
+
+        let written = write(&todos);
+        assert_eq!(parse(&written), todos);
+        assert_eq!(
+            written.lines().next().unwrap(),
+            format!(
+                "(A) 2026-10-01 Call mom +family @phone due:2026-10-20 id:{}",
+                todos[0].id
+            )
+        );
+    }
+
+    #[test]
+    fn test_round_trip_text() {
+        let mut todos: Vec<Todo> = [
+            "x marks the spot",
+            "(B) is no priority",
+            "2026-10-19 is no date",
+            "Meet at 10:30",
+            "Two\nlines",
+            "  spaced  out\t",
+            "100% done",
+        ]
+        .iter()
+        .map(|text| Todo::new(text.to_string()))
+        .collect();
+        let mut completed = Todo::new("(A) 2026-10-01 x".to_string());
+        completed.completed = true;
+        todos.push(completed);
+
+        let written = write(&todos);
+        assert_eq!(written.lines().count(), todos.len());
+        assert_eq!(parse(&written), todos);
+        assert!(written.contains("text:Meet%20at%2010%3A30 "));
+        assert!(written.lines().nth(6).unwrap().starts_with("100% done id:"));
+    }
+
+    #[test]
+    fn test_round_trip_metadata() {
+        let mut todo = Todo::new("Plain".to_string());
+        for (key, value) in [
+            ("Effort", "1:00"),
+            ("uid", "abc:def@example.com"),
+            ("annotation.20261002T120000Z", "Ask about the party"),
+            ("file", "/home/me/my project/main.rs"),
+            ("share", "//server/share"),
+            ("progress", "50%"),
+            ("key with space", "value"),
+            ("text", "not the text"),
+            ("id", "not the id"),
+            ("due", "2026-10-20"),
+        ] {
+            todo.metadata.insert(key.to_string(), value.to_string());
+        }
+        let todos = vec![todo];
+
+        let written = write(&todos);
+        assert_eq!(parse(&written), todos);
+        assert!(written.starts_with("Plain "));
+        assert!(written.contains(" due:2026-10-20 "));
+        assert!(written.contains(" %74ext:not%20the%20text "));
+    }
+}
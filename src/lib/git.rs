@@ -0,0 +1,476 @@
+use crate::{
+    errors::ApplicationError,
+    merge,
+    storage::{self, Change, Storage, StorageConfig},
+    template::Template,
+    todo::Todo,
+};
+use serde::Deserialize;
+use std::{
+    path::PathBuf,
+    process::{Command, Output},
+};
+
+/// The `git` section of the config file. If present, the data directory is a git
+/// repository and every session is committed to it.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct GitConfig {
+    /// Remote to sync with
+    #[serde(default = "default_remote")]
+    pub remote: String,
+    /// URL the remote is added with if the repository doesn't know it yet, e.g.
+    /// the path of a bare repository on a shared disk
+    #[serde(default)]
+    pub url: Option<String>,
+    /// Branch to sync, the checked out one if not set
+    #[serde(default)]
+    pub branch: Option<String>,
+}
+
+fn default_remote() -> String {
+    "origin".to_string()
+}
+
+/// The git repository holding the data. Only the data itself is ever added and
+/// committed, so it can live in a repository with other files.
+pub struct GitRepo {
+    dir: PathBuf,
+    /// The data, relative to `dir`
+    pathspec: PathBuf,
+    config: GitConfig,
+}
+
+impl GitRepo {
+    /// Opens the repository containing the data of `storage`, creating one if it
+    /// isn't in a repository yet.
+    pub fn open(config: &GitConfig, storage: &StorageConfig) -> Result<Self, ApplicationError> {
+        let (dir, pathspec) = match storage {
+            StorageConfig::Json { path, .. } => {
+                let dir = match path.parent() {
+                    Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+                    _ => PathBuf::from("."),
+                };
+                let name = path.file_name().unwrap_or(path.as_os_str());
+                (dir, PathBuf::from(name))
+            }
+            // Syncing merges the data of the remote, which needs it in a single file
+            StorageConfig::Directory { .. } | StorageConfig::Log { .. } => {
+                return Err(ApplicationError(
+                    "Only the json backend can be stored in git".to_string(),
+                ))
+            }
+            StorageConfig::Memory => {
+                return Err(ApplicationError(
+                    "The memory backend can't be stored in git".to_string(),
+                ))
+            }
+        };
+
+        std::fs::create_dir_all(&dir)?;
+        let repo = Self {
+            dir,
+            pathspec,
+            config: config.clone(),
+        };
+        if !repo.run_ok(&["rev-parse", "--is-inside-work-tree"])? {
+            repo.run(&["init", "-q"])?;
+        }
+        Ok(repo)
+    }
+
+    fn command(&self, args: &[&str]) -> Command {
+        let mut command = Command::new("git");
+        command.arg("-C").arg(&self.dir);
+        command.args(args);
+        command
+    }
+
+    fn output(&self, args: &[&str]) -> Result<Output, ApplicationError> {
+        self.command(args)
+            .output()
+            .map_err(|err| ApplicationError(format!("Failed to run git: {}", err)))
+    }
+
+    /// Runs git, failing with its error output if it fails.
+    fn run(&self, args: &[&str]) -> Result<Output, ApplicationError> {
+        let output = self.output(args)?;
+        if !output.status.success() {
+            return Err(failed(args, &output));
+        }
+        Ok(output)
+    }
+
+    /// Runs git, returning whether it succeeded.
+    fn run_ok(&self, args: &[&str]) -> Result<bool, ApplicationError> {
+        Ok(self.output(args)?.status.success())
+    }
+
+    fn run_stdout(&self, args: &[&str]) -> Result<String, ApplicationError> {
+        let output = self.run(args)?;
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    }
+
+    fn pathspec(&self) -> &str {
+        self.pathspec.to_str().unwrap_or(".")
+    }
+
+    /// Prefixes `args` with an identity if none is configured, so commits and merges
+    /// work on a fresh machine used only for syncing TODOs.
+    fn with_identity<'a>(&self, args: &[&'a str]) -> Result<Vec<&'a str>, ApplicationError> {
+        let mut full = Vec::new();
+        if !self.run_ok(&["config", "user.email"])? {
+            full.extend([
+                "-c",
+                "user.name=todo_cmd",
+                "-c",
+                "user.email=todo_cmd@localhost",
+            ]);
+        }
+        full.extend(args);
+        Ok(full)
+    }
+
+    /// Commits the data if it changed, returns whether there was anything to commit.
+    pub fn commit(&self, message: &str) -> Result<bool, ApplicationError> {
+        let pathspec = self.pathspec();
+        self.run(&["add", "-A", "--", pathspec])?;
+        if self.run_ok(&["diff", "--cached", "--quiet", "--", pathspec])? {
+            return Ok(false);
+        }
+        self.run(&self.with_identity(&["commit", "-q", "-m", message, "--", pathspec])?)?;
+        Ok(true)
+    }
+
+    fn branch(&self) -> Result<String, ApplicationError> {
+        match &self.config.branch {
+            Some(branch) => Ok(branch.clone()),
+            None => self.run_stdout(&["symbolic-ref", "--short", "HEAD"]),
+        }
+    }
+
+    /// The TODOs as stored in `revision`, none if the data didn't exist then.
+    fn load_revision(
+        &self,
+        storage: &mut dyn Storage,
+        revision: &str,
+    ) -> Result<Vec<Todo>, ApplicationError> {
+        let object = format!("{}:./{}", revision, self.pathspec());
+        if !self.run_ok(&["cat-file", "-e", &object])? {
+            return Ok(Vec::new());
+        }
+        storage.load_content(&self.run(&["show", &object])?.stdout)
+    }
+
+    /// Pulls the changes of the remote and pushes ours. `todos` must be stored and
+    /// committed already. If both sides changed, the TODOs are merged like external
+    /// changes on store, instead of leaving conflict markers in the data file.
+    ///
+    /// Returns the TODOs including the changes of the remote.
+    pub fn sync(
+        &self,
+        storage: &mut dyn Storage,
+        todos: &[Todo],
+        template: &Template,
+    ) -> Result<Vec<Todo>, ApplicationError> {
+        let remote = self.config.remote.as_str();
+        let branch = self.branch()?;
+        if let Some(url) = &self.config.url {
+            if !self.run_ok(&["remote", "get-url", remote])? {
+                self.run(&["remote", "add", remote, url])?;
+            }
+        }
+
+        let mut todos = todos.to_vec();
+        // A remote nobody pushed to yet has nothing to fetch
+        let remote_ref = format!("refs/remotes/{}/{}", remote, branch);
+        let branch_ref = format!("refs/heads/{}", branch);
+        if self.run_ok(&["ls-remote", "--exit-code", remote, &branch_ref])? {
+            self.run(&[
+                "fetch",
+                "-q",
+                remote,
+                &format!("+{}:{}", branch_ref, remote_ref),
+            ])?;
+            let has_head = self.run_ok(&["rev-parse", "-q", "--verify", "HEAD"])?;
+
+            if !has_head || self.run_ok(&["merge-base", "--is-ancestor", "HEAD", &remote_ref])? {
+                self.run(&["merge", "-q", "--ff-only", &remote_ref])?;
+                todos = storage.load()?;
+            } else if !self.run_ok(&["merge-base", "--is-ancestor", &remote_ref, "HEAD"])? {
+                todos = self.merge(storage, &todos, &remote_ref, template)?;
+            }
+        }
+
+        // Nothing to push before the first commit
+        if self.run_ok(&["rev-parse", "-q", "--verify", "HEAD"])? {
+            self.run(&["push", "-q", remote, &format!("HEAD:{}", branch_ref)])?;
+        }
+        Ok(todos)
+    }
+
+    fn merge(
+        &self,
+        storage: &mut dyn Storage,
+        ours: &[Todo],
+        remote_ref: &str,
+        template: &Template,
+    ) -> Result<Vec<Todo>, ApplicationError> {
+        // Histories are unrelated if both sides started the list on their own
+        let merge_base = self.output(&["merge-base", "HEAD", remote_ref])?;
+        let base = match merge_base.status.success() {
+            true => {
+                let merge_base = String::from_utf8_lossy(&merge_base.stdout);
+                self.load_revision(storage, merge_base.trim())?
+            }
+            false => Vec::new(),
+        };
+        let theirs = self.load_revision(storage, remote_ref)?;
+
+        let mut result = merge::three_way_merge(&base, ours, &theirs);
+        if !result.conflicts.is_empty() {
+            println!(
+                "{} TODO(s) were changed on both sides.",
+                result.conflicts.len()
+            );
+            merge::resolve_conflicts(&mut result, template)?;
+        }
+
+        // Git merges everything else, the data file may conflict but is replaced below
+        let merge = [
+            "merge",
+            "-q",
+            "--no-ff",
+            "--no-commit",
+            "--allow-unrelated-histories",
+            remote_ref,
+        ];
+        let output = self.output(&self.with_identity(&merge)?)?;
+        // Conflicts are expected, failing before even starting the merge is not
+        if !output.status.success()
+            && !self.run_ok(&["rev-parse", "-q", "--verify", "MERGE_HEAD"])?
+        {
+            return Err(failed(&merge, &output));
+        }
+        storage.save(&result.todos)?;
+        self.run(&["add", "-A", "--", self.pathspec()])?;
+
+        let unmerged = self.run_stdout(&["diff", "--name-only", "--diff-filter=U"])?;
+        if !unmerged.is_empty() {
+            self.run(&["merge", "--abort"])?;
+            storage.save(ours)?;
+            return Err(ApplicationError(format!(
+                "Other files in the repository conflict, merge them with git: {}",
+                unmerged.replace('\n', ", ")
+            )));
+        }
+
+        let message = format!(
+            "Merge TODOs from {}",
+            remote_ref.trim_start_matches("refs/remotes/")
+        );
+        self.run(&self.with_identity(&["commit", "-q", "-m", &message])?)?;
+        Ok(result.todos)
+    }
+}
+
+fn failed(args: &[&str], output: &Output) -> ApplicationError {
+    ApplicationError(format!(
+        "git {} failed: {}",
+        args.join(" "),
+        String::from_utf8_lossy(&output.stderr).trim()
+    ))
+}
+
+/// Describes the changes from `before` to `after` as a commit message, a summary
+/// like "Add 2 TODOs, complete 1 TODO" followed by a line per change.
+pub fn describe_changes(before: &[Todo], after: &[Todo]) -> Option<String> {
+    // Verbs in the order of the summary
+    let verbs = ["add", "complete", "reopen", "edit", "delete", "reorder"];
+    let mut counts = [0usize; 6];
+    let mut details = Vec::new();
+
+    for change in storage::diff(before, after) {
+        match change {
+            Change::Insert { todo } => {
+                counts[0] += 1;
+                details.push(format!("- Add: {}", todo.text));
+            }
+            Change::Update { todo } => {
+                let Some(old) = before.iter().find(|old| old.id == todo.id) else {
+                    continue;
+                };
+                if old.completed != todo.completed {
+                    let i = if todo.completed { 1 } else { 2 };
+                    counts[i] += 1;
+                    details.push(format!("- {}: {}", capitalize(verbs[i]), todo.text));
+                }
+                if old.text != todo.text {
+                    counts[3] += 1;
+                    details.push(format!("- Edit: {} -> {}", old.text, todo.text));
+                }
+            }
+            Change::Remove { id } => {
+                counts[4] += 1;
+                if let Some(old) = before.iter().find(|old| old.id == id) {
+                    details.push(format!("- Delete: {}", old.text));
+                }
+            }
+            Change::Reorder { .. } => counts[5] += 1,
+        }
+    }
+
+    let summary: Vec<String> = verbs
+        .iter()
+        .zip(counts)
+        .filter(|(_, count)| *count > 0)
+        .map(|(verb, count)| match *verb {
+            "reorder" => "reorder TODOs".to_string(),
+            verb => format!(
+                "{} {} TODO{}",
+                verb,
+                count,
+                if count == 1 { "" } else { "s" }
+            ),
+        })
+        .collect();
+    if summary.is_empty() {
+        return None;
+    }
+
+    let mut message = capitalize(&summary.join(", "));
+    if !details.is_empty() {
+        message.push_str("\n\n");
+        message.push_str(&details.join("\n"));
+    }
+    Some(message)
+}
+
+fn capitalize(text: &str) -> String {
+    let mut chars = text.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::{tests::temp_path, JsonFileStorage};
+
+    #[test]
+    fn test_describe_changes() {
+        let first = Todo::new("first".to_string());
+        let second = Todo::new("second".to_string());
+        let before = vec![first.clone(), second.clone()];
+        assert_eq!(describe_changes(&before, &before), None);
+
+        let mut completed = first.clone();
+        completed.completed = true;
+        let after = vec![
+            completed,
+            Todo::new("third".to_string()),
+            Todo::new("fourth".to_string()),
+        ];
+        assert_eq!(
+            describe_changes(&before, &after).unwrap(),
+            "Add 2 TODOs, complete 1 TODO, delete 1 TODO\n\n\
+             - Delete: second\n- Complete: first\n- Add: third\n- Add: fourth"
+        );
+    }
+
+    fn storage_config(path: &std::path::Path) -> StorageConfig {
+        StorageConfig::Json {
+            path: path.join("todos.json"),
+            encrypted: false,
+            pretty: false,
+        }
+    }
+
+    #[test]
+    fn test_open_needs_single_file() {
+        let config = GitConfig {
+            remote: default_remote(),
+            url: None,
+            branch: None,
+        };
+        let dir = temp_path("git_backends");
+        for storage in [
+            StorageConfig::Directory { path: dir.clone() },
+            StorageConfig::Log {
+                path: dir.clone(),
+                compact_after: 500,
+            },
+            StorageConfig::Memory,
+        ] {
+            assert!(GitRepo::open(&config, &storage).is_err());
+        }
+        assert!(!dir.join(".git").exists());
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_sync_merges_todos() {
+        let dir = temp_path("git_sync");
+        let remote = dir.join("remote.git");
+        Command::new("git")
+            .args(["init", "-q", "--bare"])
+            .arg(&remote)
+            .status()
+            .unwrap();
+        let config = GitConfig {
+            remote: "origin".to_string(),
+            url: Some(remote.to_string_lossy().to_string()),
+            branch: Some("main".to_string()),
+        };
+
+        let template = Template::default();
+        // The first machine creates the list, the second one starts from it
+        let first = Todo::new("first".to_string());
+        let first_dir = dir.join("first");
+        let first_repo = GitRepo::open(&config, &storage_config(&first_dir)).unwrap();
+        let mut first_storage = JsonFileStorage::new(first_dir.join("todos.json"));
+        first_storage.save(std::slice::from_ref(&first)).unwrap();
+        assert!(first_repo.commit("Add first").unwrap());
+        assert!(!first_repo.commit("Nothing").unwrap());
+        first_repo
+            .sync(&mut first_storage, std::slice::from_ref(&first), &template)
+            .unwrap();
+
+        let second_dir = dir.join("second");
+        let second_repo = GitRepo::open(&config, &storage_config(&second_dir)).unwrap();
+        let mut second_storage = JsonFileStorage::new(second_dir.join("todos.json"));
+        let todos = second_repo
+            .sync(&mut second_storage, &[], &template)
+            .unwrap();
+        assert_eq!(todos, vec![first.clone()]);
+
+        // Both change the same file, which git alone can't merge
+        let mut completed = first.clone();
+        completed.completed = true;
+        second_storage
+            .save(std::slice::from_ref(&completed))
+            .unwrap();
+        second_repo.commit("Complete first").unwrap();
+        second_repo
+            .sync(
+                &mut second_storage,
+                std::slice::from_ref(&completed),
+                &template,
+            )
+            .unwrap();
+
+        let added = Todo::new("added".to_string());
+        let ours = vec![first.clone(), added.clone()];
+        first_storage.save(&ours).unwrap();
+        first_repo.commit("Add added").unwrap();
+        let todos = first_repo
+            .sync(&mut first_storage, &ours, &template)
+            .unwrap();
+        assert_eq!(todos, vec![completed, added]);
+        assert_eq!(first_storage.load().unwrap(), todos);
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
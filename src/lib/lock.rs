@@ -0,0 +1,91 @@
+use std::{
+    fs::{File, OpenOptions},
+    io::{self, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+};
+
+/// Advisory lock guarding a data file against concurrent instances.
+///
+/// The lock is taken on a `<data file>.lock` sibling which also records the PID of
+/// the holder. The OS releases the lock when the holding process exits, so a lock
+/// file left behind by a crashed instance is never mistaken for a live one.
+pub struct FileLock {
+    file: File,
+    path: PathBuf,
+}
+
+impl FileLock {
+    /// Tries to lock `data_path` without blocking. Fails with
+    /// `io::ErrorKind::WouldBlock` if another instance holds the lock.
+    pub fn acquire(data_path: &Path) -> io::Result<FileLock> {
+        let path = lock_path(data_path);
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false) // Must not clear the holder's PID before we own the lock
+            .open(&path)?;
+
+        file.try_lock().map_err(io::Error::from)?;
+
+        file.set_len(0)?;
+        file.seek(SeekFrom::Start(0))?;
+        write!(file, "{}", std::process::id())?;
+        file.sync_all()?;
+
+        Ok(FileLock { file, path })
+    }
+
+    /// Returns the PID recorded by the current holder of the lock on `data_path`, if any.
+    pub fn holder(data_path: &Path) -> Option<u32> {
+        let mut content = String::new();
+        File::open(lock_path(data_path))
+            .ok()?
+            .read_to_string(&mut content)
+            .ok()?;
+        content.trim().parse().ok()
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for FileLock {
+    fn drop(&mut self) {
+        // The lock file is kept around, removing it would race with instances
+        // which already opened it. Clearing the PID is enough to mark it as free.
+        let _ = self.file.set_len(0);
+        let _ = self.file.unlock();
+    }
+}
+
+fn lock_path(data_path: &Path) -> PathBuf {
+    let mut name = data_path.as_os_str().to_owned();
+    name.push(".lock");
+    PathBuf::from(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_second_lock_fails_until_released() {
+        let data_path =
+            std::env::temp_dir().join(format!("todo_lock_test_{}.json", std::process::id()));
+
+        let lock = FileLock::acquire(&data_path).unwrap();
+        assert_eq!(FileLock::holder(&data_path), Some(std::process::id()));
+
+        let err = FileLock::acquire(&data_path).err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::WouldBlock);
+
+        let lock_file = lock.path().to_path_buf();
+        drop(lock);
+        assert_eq!(FileLock::holder(&data_path), None);
+        assert!(FileLock::acquire(&data_path).is_ok());
+
+        let _ = std::fs::remove_file(lock_file);
+    }
+}
@@ -0,0 +1,279 @@
+use crate::{
+    errors::{ApplicationError, SelectionError},
+    get_input,
+    template::Template,
+    todo::Todo,
+};
+use std::io::{stdout, Write};
+use uuid::Uuid;
+
+/// A TODO both sides changed in incompatible ways. `None` means the side deleted it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Conflict {
+    pub id: Uuid,
+    pub ours: Option<Todo>,
+    pub theirs: Option<Todo>,
+}
+
+#[derive(Debug, Default)]
+pub struct MergeResult {
+    /// The merged list. A conflicting TODO is kept at its place with our version
+    /// (or theirs, if we deleted it) until the conflict is resolved.
+    pub todos: Vec<Todo>,
+    pub conflicts: Vec<Conflict>,
+}
+
+/// Merges `ours` and `theirs`, which were both derived from `base`.
+///
+/// TODOs are matched by id and merged field by field, so changing the text on one
+/// side and completing it on the other doesn't conflict. The order of `ours` is kept,
+/// TODOs only added by them are appended.
+pub fn three_way_merge(base: &[Todo], ours: &[Todo], theirs: &[Todo]) -> MergeResult {
+    let find = |todos: &[Todo], id: Uuid| todos.iter().find(|todo| todo.id == id).cloned();
+    let mut result = MergeResult::default();
+
+    for our in ours {
+        match (find(base, our.id), find(theirs, our.id)) {
+            (Some(base), Some(their)) => match merge_todo(&base, our, &their) {
+                Some(merged) => result.todos.push(merged),
+                None => result.add_conflict(Some(our.clone()), Some(their)),
+            },
+            // Deleted by them, keep it deleted unless we changed it
+            (Some(base), None) => {
+                if base != *our {
+                    result.add_conflict(Some(our.clone()), None);
+                }
+            }
+            (None, Some(their)) => {
+                if their == *our {
+                    result.todos.push(their);
+                } else {
+                    result.add_conflict(Some(our.clone()), Some(their));
+                }
+            }
+            (None, None) => result.todos.push(our.clone()),
+        }
+    }
+
+    for their in theirs.iter().filter(|todo| find(ours, todo.id).is_none()) {
+        match find(base, their.id) {
+            // Deleted by us, keep it deleted unless they changed it
+            Some(base) => {
+                if base != *their {
+                    result.add_conflict(None, Some(their.clone()));
+                }
+            }
+            None => result.todos.push(their.clone()),
+        }
+    }
+
+    result
+}
+
+fn merge_todo(base: &Todo, ours: &Todo, theirs: &Todo) -> Option<Todo> {
+    Some(Todo {
+        id: ours.id,
+        text: merge_field(&base.text, &ours.text, &theirs.text)?,
+        completed: merge_field(&base.completed, &ours.completed, &theirs.completed)?,
+        priority: merge_field(&base.priority, &ours.priority, &theirs.priority)?,
+        created: merge_field(&base.created, &ours.created, &theirs.created)?,
+        completed_at: merge_field(&base.completed_at, &ours.completed_at, &theirs.completed_at)?,
+        list: merge_field(&base.list, &ours.list, &theirs.list)?,
+        parent: merge_field(&base.parent, &ours.parent, &theirs.parent)?,
+        metadata: merge_field(&base.metadata, &ours.metadata, &theirs.metadata)?,
+    })
+}
+
+fn merge_field<T: PartialEq + Clone>(base: &T, ours: &T, theirs: &T) -> Option<T> {
+    if ours == theirs || theirs == base {
+        Some(ours.clone())
+    } else if ours == base {
+        Some(theirs.clone())
+    } else {
+        None
+    }
+}
+
+impl MergeResult {
+    fn add_conflict(&mut self, ours: Option<Todo>, theirs: Option<Todo>) {
+        let placeholder = ours.clone().or_else(|| theirs.clone());
+        if let Some(todo) = placeholder {
+            self.conflicts.push(Conflict {
+                id: todo.id,
+                ours,
+                theirs,
+            });
+            self.todos.push(todo);
+        }
+    }
+}
+
+fn describe(todo: &Option<Todo>, template: &Template) -> String {
+    match todo {
+        Some(todo) => template.render(todo),
+        None => "<deleted>".to_string(),
+    }
+}
+
+fn resolve_conflicts_internal<F>(
+    result: &mut MergeResult,
+    template: &Template,
+    mut get_input: F,
+) -> Result<(), ApplicationError>
+where
+    F: FnMut() -> Result<String, std::io::Error>,
+{
+    for conflict in std::mem::take(&mut result.conflicts) {
+        println!("\nConflicting changes:");
+        println!("  [O]urs:   {}", describe(&conflict.ours, template));
+        println!("  [T]heirs: {}", describe(&conflict.theirs, template));
+
+        // Asks again on invalid input, giving up here would lose the changes of one side
+        let chosen = loop {
+            print!("Which version should be kept? ");
+            let _ = stdout().flush();
+            match get_input()?.as_str() {
+                "O" | "o" => break conflict.ours,
+                "T" | "t" => break conflict.theirs,
+                input => println!("{}", SelectionError(input.to_string())),
+            }
+        };
+
+        let position = result.todos.iter().position(|todo| todo.id == conflict.id);
+        match (position, chosen) {
+            (Some(i), Some(todo)) => result.todos[i] = todo,
+            (Some(i), None) => {
+                result.todos.remove(i);
+            }
+            (None, Some(todo)) => result.todos.push(todo),
+            (None, None) => {}
+        }
+    }
+    Ok(())
+}
+
+/// Asks the user which side to keep for every conflict of `result`, showing both
+/// with `template`.
+pub fn resolve_conflicts(
+    result: &mut MergeResult,
+    template: &Template,
+) -> Result<(), ApplicationError> {
+    resolve_conflicts_internal(result, template, get_input)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn todo(text: &str, completed: bool) -> Todo {
+        let mut todo = Todo::new(text.to_string());
+        todo.completed = completed;
+        todo
+    }
+
+    fn changed(todo: &Todo, text: &str, completed: bool) -> Todo {
+        Todo {
+            text: text.to_string(),
+            completed,
+            ..todo.clone()
+        }
+    }
+
+    #[test]
+    fn test_merge_independent_changes() {
+        let first = todo("first", false);
+        let second = todo("second", false);
+        let base = vec![first.clone(), second.clone()];
+
+        let added_by_us = todo("ours", false);
+        let added_by_them = todo("theirs", false);
+        let ours = vec![changed(&first, "first edited", false), added_by_us.clone()];
+        let theirs = vec![
+            changed(&first, "first", true),
+            second.clone(),
+            added_by_them.clone(),
+        ];
+
+        let result = three_way_merge(&base, &ours, &theirs);
+        assert!(result.conflicts.is_empty());
+        assert_eq!(
+            result.todos,
+            vec![
+                changed(&first, "first edited", true),
+                added_by_us,
+                added_by_them
+            ]
+        );
+    }
+
+    #[test]
+    fn test_merge_conflicts() {
+        let first = todo("first", false);
+        let second = todo("second", false);
+        let third = todo("third", false);
+        let base = vec![first.clone(), second.clone(), third.clone()];
+
+        // Both edit the text of the first, we edit the second they delete, they edit the third we delete
+        let ours = vec![
+            changed(&first, "ours", false),
+            changed(&second, "ours", false),
+        ];
+        let theirs = vec![
+            changed(&first, "theirs", false),
+            changed(&third, "theirs", false),
+        ];
+
+        let mut result = three_way_merge(&base, &ours, &theirs);
+        assert_eq!(result.conflicts.len(), 3);
+        assert_eq!(result.todos.len(), 3);
+        assert_eq!(result.conflicts[1].theirs, None);
+        assert_eq!(result.conflicts[2].ours, None);
+
+        let mut inputs = vec!["T", "t", "O"].into_iter();
+        let res = resolve_conflicts_internal(&mut result, &Template::default(), || {
+            Ok(inputs.next().unwrap().to_string())
+        });
+        assert!(res.is_ok());
+        assert!(result.conflicts.is_empty());
+        assert_eq!(result.todos, vec![changed(&first, "theirs", false)]);
+    }
+
+    #[test]
+    fn test_resolve_asks_again_on_invalid_selection() {
+        let first = todo("first", false);
+        let mut result = three_way_merge(
+            std::slice::from_ref(&first),
+            &[changed(&first, "ours", false)],
+            &[changed(&first, "theirs", false)],
+        );
+
+        let mut inputs = vec!["x", "", "o"].into_iter();
+        let res = resolve_conflicts_internal(&mut result, &Template::default(), || {
+            Ok(inputs.next().unwrap().to_string())
+        });
+        assert!(res.is_ok());
+        assert_eq!(result.todos, vec![changed(&first, "ours", false)]);
+
+        let mut result = three_way_merge(
+            std::slice::from_ref(&first),
+            &[changed(&first, "ours", false)],
+            &[changed(&first, "theirs", false)],
+        );
+        let res = resolve_conflicts_internal(&mut result, &Template::default(), || {
+            Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, ""))
+        });
+        assert!(res.is_err()); // Input Error
+    }
+
+    #[test]
+    fn test_describe() {
+        let todo = todo("first", true);
+        let template = Template::parse("[{check}] {text}").unwrap();
+        assert_eq!(describe(&Some(todo.clone()), &template), "[x] first");
+        assert_eq!(
+            describe(&Some(todo), &Template::default()),
+            "completed: true | text: first"
+        );
+        assert_eq!(describe(&None, &template), "<deleted>");
+    }
+}
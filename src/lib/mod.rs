@@ -1,10 +1,28 @@
-use std::{cell::RefCell, io, sync::Arc};
+use std::{
+    cell::RefCell,
+    io::{self, IsTerminal},
+    rc::Rc,
+};
 
 pub mod action;
+pub mod cli;
+pub mod config;
+pub mod crdt;
+pub mod doctor;
 pub mod todo;
 pub mod errors;
+pub mod formats;
+pub mod git;
+pub mod lock;
+pub mod merge;
+pub mod output;
+pub mod query;
+pub mod scan;
+pub mod snapshot;
+pub mod storage;
+pub mod template;
 
-pub type Todos = Arc<RefCell<Vec<todo::Todo>>>;
+pub type Todos = Rc<RefCell<Vec<todo::Todo>>>;
 
 pub fn get_input() -> Result<String, std::io::Error> {
     let mut input = String::new();
@@ -16,8 +34,17 @@ pub fn get_input() -> Result<String, std::io::Error> {
             if let Some('\r') = input.chars().next_back() {
                 input.pop();
             }
-            return Ok(input);
+            Ok(input)
         }
         Err(err) => Err(err),
     }
-}
\ No newline at end of file
+}
+
+/// Like `get_input`, but doesn't echo what is typed into a terminal.
+pub fn get_secret_input() -> Result<String, std::io::Error> {
+    if io::stdin().is_terminal() {
+        rpassword::read_password()
+    } else {
+        get_input()
+    }
+}
@@ -0,0 +1,279 @@
+//! Machine-readable output of the subcommands, for scripts and editor plugins.
+//!
+//! `--format json` prints one JSON object per command, `--format jsonl` the same
+//! object on a single line, except for `list`, which prints a line per TODO, and
+//! `batch` and commands with `--where`, which print a line per result. Fields
+//! are only ever added to this schema, never renamed, removed or changed in meaning.
+//!
+//! A TODO is printed as
+//!
+//! ```text
+//! {"number": 1, "id": "<uuid>", "text": "...", "completed": false,
+//!  "priority": "A" | null, "created": "YYYY-MM-DD" | null,
+//!  "completed_at": "YYYY-MM-DD" | null, "due": "YYYY-MM-DD" | null,
+//!  "list": "..." | null, "parent": "<uuid>" | null, "metadata": {"key": "value"}}
+//! ```
+//!
+//! where `number` is what the TODO is selected with, as of before the command ran.
+//! The results of the commands are
+//!
+//! - `{"command": "list", "todos": [<todo>...]}`
+//! - `{"command": "add" | "done" | "edit" | "rm" | "tag", "todo": <todo>}`, the TODO
+//!   after the command
+//! - `{"command": "batch", "results": [<result>...]}`, a result per command of the
+//!   script, or per TODO changed by a command with `--where`
+//! - `{"command": "doctor", "findings": [{"problem": "...", "fix": "..." | null}],
+//!   "repairable": bool, "dry_run": bool, "backup": "<path>" | null,
+//!   "content_checked": bool}`, the last one `false` if only the TODOs the backend
+//!   loaded were checked, not its files
+//!
+//! and errors are `{"error": {"code": "<code>", "message": "..."}}`, the code being
+//! `invalid_arguments`, `invalid_selection`, `application_error` or `conflict`, if
+//! another program changed the same TODOs meanwhile and nothing was stored.
+
+use crate::{
+    cli::{Error, Outcome},
+    doctor::Finding,
+    todo::Todo,
+};
+use chrono::NaiveDate;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum OutputFormat {
+    #[default]
+    Text,
+    Json,
+    Jsonl,
+}
+
+impl OutputFormat {
+    pub fn from_name(name: &str) -> Option<OutputFormat> {
+        match name {
+            "text" => Some(OutputFormat::Text),
+            "json" => Some(OutputFormat::Json),
+            "jsonl" => Some(OutputFormat::Jsonl),
+            _ => None,
+        }
+    }
+}
+
+/// A TODO as printed, independent of how it is stored.
+#[derive(Serialize)]
+struct TodoOutput<'a> {
+    number: usize,
+    id: Uuid,
+    text: &'a str,
+    completed: bool,
+    priority: Option<char>,
+    created: Option<NaiveDate>,
+    completed_at: Option<NaiveDate>,
+    due: Option<NaiveDate>,
+    list: Option<&'a str>,
+    parent: Option<Uuid>,
+    metadata: &'a BTreeMap<String, String>,
+}
+
+impl<'a> TodoOutput<'a> {
+    fn new(number: usize, todo: &'a Todo) -> Self {
+        TodoOutput {
+            number,
+            id: todo.id,
+            text: &todo.text,
+            completed: todo.completed,
+            priority: todo.priority,
+            created: todo.created,
+            completed_at: todo.completed_at,
+            due: todo.due(),
+            list: todo.list.as_deref(),
+            parent: todo.parent,
+            metadata: &todo.metadata,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct FindingOutput<'a> {
+    problem: &'a str,
+    fix: Option<&'a str>,
+}
+
+#[derive(Serialize)]
+#[serde(tag = "command", rename_all = "lowercase")]
+enum OutcomeOutput<'a> {
+    List {
+        todos: Vec<TodoOutput<'a>>,
+    },
+    Add {
+        todo: TodoOutput<'a>,
+    },
+    Done {
+        todo: TodoOutput<'a>,
+    },
+    Edit {
+        todo: TodoOutput<'a>,
+    },
+    Rm {
+        todo: TodoOutput<'a>,
+    },
+    Tag {
+        todo: TodoOutput<'a>,
+    },
+    Batch {
+        results: Vec<OutcomeOutput<'a>>,
+    },
+    Doctor {
+        findings: Vec<FindingOutput<'a>>,
+        repairable: bool,
+        dry_run: bool,
+        backup: Option<String>,
+        content_checked: bool,
+    },
+}
+
+impl<'a> OutcomeOutput<'a> {
+    fn new(outcome: &'a Outcome) -> Self {
+        match outcome {
+            Outcome::List(todos) => OutcomeOutput::List {
+                todos: todos
+                    .iter()
+                    .map(|(number, todo)| TodoOutput::new(*number, todo))
+                    .collect(),
+            },
+            Outcome::Added(number, todo) => OutcomeOutput::Add {
+                todo: TodoOutput::new(*number, todo),
+            },
+            Outcome::Completed(number, todo) => OutcomeOutput::Done {
+                todo: TodoOutput::new(*number, todo),
+            },
+            Outcome::Edited(number, todo) => OutcomeOutput::Edit {
+                todo: TodoOutput::new(*number, todo),
+            },
+            Outcome::Removed(number, todo) => OutcomeOutput::Rm {
+                todo: TodoOutput::new(*number, todo),
+            },
+            Outcome::Tagged(number, todo) => OutcomeOutput::Tag {
+                todo: TodoOutput::new(*number, todo),
+            },
+            Outcome::Batch(outcomes) => OutcomeOutput::Batch {
+                results: outcomes.iter().map(OutcomeOutput::new).collect(),
+            },
+            Outcome::Doctor {
+                findings,
+                repairable,
+                dry_run,
+                backup,
+                content_checked,
+            } => OutcomeOutput::Doctor {
+                findings: findings.iter().map(FindingOutput::new).collect(),
+                repairable: *repairable,
+                dry_run: *dry_run,
+                backup: backup.as_ref().map(|path| path.display().to_string()),
+                content_checked: *content_checked,
+            },
+        }
+    }
+}
+
+impl<'a> FindingOutput<'a> {
+    fn new(finding: &'a Finding) -> Self {
+        FindingOutput {
+            problem: &finding.problem,
+            fix: finding.fix.as_deref(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct ErrorOutput<'a> {
+    code: &'a str,
+    message: String,
+}
+
+/// The JSON of a command's outcome, with a trailing newline.
+pub fn outcome(outcome: &Outcome, format: OutputFormat) -> String {
+    let output = OutcomeOutput::new(outcome);
+    match (format, output) {
+        (OutputFormat::Jsonl, OutcomeOutput::List { todos }) => {
+            todos.iter().map(|todo| to_line(todo, format)).collect()
+        }
+        (OutputFormat::Jsonl, OutcomeOutput::Batch { results }) => results
+            .iter()
+            .map(|result| to_line(result, format))
+            .collect(),
+        (format, output) => to_line(&output, format),
+    }
+}
+
+/// The JSON of an error, with a trailing newline.
+pub fn error(error: &Error, format: OutputFormat) -> String {
+    let output = ErrorOutput {
+        code: error.code(),
+        message: error.message(),
+    };
+    to_line(&BTreeMap::from([("error", output)]), format)
+}
+
+fn to_line<T: Serialize>(value: &T, format: OutputFormat) -> String {
+    let json = match format {
+        OutputFormat::Jsonl => serde_json::to_string(value),
+        _ => serde_json::to_string_pretty(value),
+    };
+    // Only maps with string keys are serialized, which can't fail
+    json.expect("Failed to serialize output") + "\n"
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::errors::SelectionError;
+
+    #[test]
+    fn test_output() {
+        let mut todo = Todo::new("Call mom".to_string());
+        todo.set_due(NaiveDate::from_ymd_opt(2026, 10, 19));
+        let id = todo.id;
+
+        let json = outcome(&Outcome::Completed(2, todo.clone()), OutputFormat::Json);
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(
+            value,
+            serde_json::json!({
+                "command": "done",
+                "todo": {
+                    "number": 2,
+                    "id": id.to_string(),
+                    "text": "Call mom",
+                    "completed": false,
+                    "priority": null,
+                    "created": null,
+                    "completed_at": null,
+                    "due": "2026-10-19",
+                    "list": null,
+                    "parent": null,
+                    "metadata": {"due": "2026-10-19"}
+                }
+            })
+        );
+
+        let lines = outcome(
+            &Outcome::List(vec![(1, todo.clone()), (2, todo)]),
+            OutputFormat::Jsonl,
+        );
+        assert_eq!(lines.lines().count(), 2);
+        assert!(lines.lines().nth(1).unwrap().starts_with("{\"number\":2,"));
+
+        let err = Error::Selection(SelectionError("There is no TODO 9".to_string()));
+        assert_eq!(
+            error(&err, OutputFormat::Jsonl),
+            "{\"error\":{\"code\":\"invalid_selection\",\"message\":\"There is no TODO 9\"}}\n"
+        );
+        let err = Error::Conflict("1 TODO(s) were changed".to_string());
+        assert_eq!(
+            error(&err, OutputFormat::Jsonl),
+            "{\"error\":{\"code\":\"conflict\",\"message\":\"1 TODO(s) were changed\"}}\n"
+        );
+    }
+}
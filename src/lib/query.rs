@@ -0,0 +1,650 @@
+//! A small language to filter TODOs, e.g.
+//! `status:open and (tag:bug or priority>=high) and due<+7d and text~"deploy"`.
+//!
+//! A filter is made of conditions `<field><operator><value>`, combined with `and`,
+//! `or`, `not` and parentheses. Conditions next to each other must both hold, as if
+//! joined with `and`. Values with spaces or operators are put in `"..."`.
+//!
+//! - `status` is `open` or `completed`.
+//! - `text` and `meta.<key>`, a metadata value, are compared as text: `:` tests if
+//!   the value is contained, ignoring case, `~` matches a regular expression.
+//! - `tag` tests for a `+project` or `@context` tag, any of both without prefix.
+//! - `priority` is a letter or `high`, `medium` or `low`, higher being more urgent.
+//! - `due`, `created` and `completed` are dates: `YYYY-MM-DD`, `today`, `tomorrow`,
+//!   `yesterday` or days and weeks from today like `+7d` or `-2w`.
+//! - `list` is the name of the list, `id` the id of the TODO or the start of it.
+//!
+//! Operators are `:`, `=` and `!=`, and `<`, `<=`, `>`, `>=` for priorities and
+//! dates. Every field but `status`, `tag` and `id` can be tested for `none`.
+
+use crate::{errors::ApplicationError, todo::Todo};
+use chrono::{Days, NaiveDate};
+use regex::{Regex, RegexBuilder};
+use std::cmp::Ordering;
+
+const FIELDS: &str = "status, text, tag, priority, due, created, completed, list, id or meta.<key>";
+
+/// A parsed filter.
+#[derive(Debug, Clone)]
+pub struct Filter {
+    source: String,
+    expr: Expr,
+}
+
+impl PartialEq for Filter {
+    fn eq(&self, other: &Self) -> bool {
+        self.source == other.source
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Expr {
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    Condition(Condition),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Field {
+    Status,
+    Text,
+    Tag,
+    Priority,
+    Due,
+    Created,
+    Completed,
+    List,
+    Id,
+    Meta(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Op {
+    /// `:`
+    Has,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    /// `~`
+    Matches,
+}
+
+#[derive(Debug, Clone)]
+enum Value {
+    None,
+    Completed(bool),
+    Text(String),
+    Regex(Regex),
+    Priority(char),
+    Date(Date),
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Date {
+    Absolute(NaiveDate),
+    /// Days from the day the filter is evaluated
+    Relative(i64),
+}
+
+#[derive(Debug, Clone)]
+struct Condition {
+    field: Field,
+    op: Op,
+    value: Value,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Open,
+    Close,
+    Word(String),
+    Quoted(String),
+    Op(Op),
+}
+
+/// A token and the column it starts at, starting at 1.
+type Located = (Token, usize);
+
+impl Filter {
+    pub fn parse(source: &str) -> Result<Filter, ApplicationError> {
+        let error = |(message, column): (String, usize)| {
+            ApplicationError(format!(
+                "Invalid filter at column {}: {}\n  {}\n  {}^",
+                column,
+                message,
+                source,
+                " ".repeat(column - 1)
+            ))
+        };
+        let tokens = tokenize(source).map_err(error)?;
+        let mut parser = Parser {
+            tokens: &tokens,
+            pos: 0,
+            end: source.chars().count() + 1,
+        };
+        let expr = parser.parse_or().map_err(error)?;
+        if let Some((_, column)) = parser.tokens.get(parser.pos) {
+            return Err(error(("unexpected )".to_string(), *column)));
+        }
+        Ok(Filter {
+            source: source.to_string(),
+            expr,
+        })
+    }
+
+    /// Whether the TODO matches, with relative dates counted from `today`.
+    pub fn matches(&self, todo: &Todo, today: NaiveDate) -> bool {
+        evaluate(&self.expr, todo, today)
+    }
+}
+
+fn tokenize(source: &str) -> Result<Vec<Located>, (String, usize)> {
+    let mut tokens = Vec::new();
+    let mut chars = source.chars().zip(1..).peekable();
+    let is_special = |c: char| "()\":=!<>~".contains(c) || c.is_whitespace();
+
+    while let Some((c, column)) = chars.next() {
+        let next_is = |chars: &mut std::iter::Peekable<_>, expected: char| {
+            chars
+                .next_if(|&(c, _): &(char, usize)| c == expected)
+                .is_some()
+        };
+        let token = match c {
+            c if c.is_whitespace() => continue,
+            '(' => Token::Open,
+            ')' => Token::Close,
+            ':' => Token::Op(Op::Has),
+            '=' => Token::Op(Op::Eq),
+            '~' => Token::Op(Op::Matches),
+            '!' if next_is(&mut chars, '=') => Token::Op(Op::Ne),
+            '!' => return Err(("expected !=".to_string(), column)),
+            '<' if next_is(&mut chars, '=') => Token::Op(Op::Le),
+            '<' => Token::Op(Op::Lt),
+            '>' if next_is(&mut chars, '=') => Token::Op(Op::Ge),
+            '>' => Token::Op(Op::Gt),
+            '"' => {
+                let mut text = String::new();
+                loop {
+                    match chars.next() {
+                        Some(('"', _)) => break,
+                        Some(('\\', _)) => match chars.next() {
+                            Some((c, _)) => text.push(c),
+                            None => return Err(("\" isn't closed".to_string(), column)),
+                        },
+                        Some((c, _)) => text.push(c),
+                        None => return Err(("\" isn't closed".to_string(), column)),
+                    }
+                }
+                Token::Quoted(text)
+            }
+            c => {
+                let mut word = c.to_string();
+                while let Some((c, _)) = chars.next_if(|&(c, _)| !is_special(c)) {
+                    word.push(c);
+                }
+                Token::Word(word)
+            }
+        };
+        tokens.push((token, column));
+    }
+    Ok(tokens)
+}
+
+struct Parser<'a> {
+    tokens: &'a [Located],
+    pos: usize,
+    /// The column after the source, where errors about its end point to
+    end: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|(token, _)| token)
+    }
+
+    fn column(&self) -> usize {
+        self.tokens
+            .get(self.pos)
+            .map_or(self.end, |(_, column)| *column)
+    }
+
+    fn next(&mut self) -> Option<Located> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn is_keyword(&self, keyword: &str) -> bool {
+        matches!(self.peek(), Some(Token::Word(word)) if word.eq_ignore_ascii_case(keyword))
+    }
+
+    fn parse_or(&mut self) -> Result<Expr, (String, usize)> {
+        let mut expr = self.parse_and()?;
+        while self.is_keyword("or") {
+            self.pos += 1;
+            expr = Expr::Or(Box::new(expr), Box::new(self.parse_and()?));
+        }
+        Ok(expr)
+    }
+
+    fn parse_and(&mut self) -> Result<Expr, (String, usize)> {
+        let mut expr = self.parse_not()?;
+        loop {
+            if self.is_keyword("and") {
+                self.pos += 1;
+            } else if self.peek().is_none()
+                || self.peek() == Some(&Token::Close)
+                || self.is_keyword("or")
+            {
+                return Ok(expr);
+            }
+            expr = Expr::And(Box::new(expr), Box::new(self.parse_not()?));
+        }
+    }
+
+    fn parse_not(&mut self) -> Result<Expr, (String, usize)> {
+        if self.is_keyword("not") {
+            self.pos += 1;
+            return Ok(Expr::Not(Box::new(self.parse_not()?)));
+        }
+        self.parse_primary()
+    }
+
+    fn parse_primary(&mut self) -> Result<Expr, (String, usize)> {
+        let column = self.column();
+        match self.next() {
+            Some((Token::Open, _)) => {
+                let expr = self.parse_or()?;
+                match self.next() {
+                    Some((Token::Close, _)) => Ok(expr),
+                    _ => Err(("( isn't closed".to_string(), column)),
+                }
+            }
+            Some((Token::Word(name), _)) => {
+                let field = parse_field(&name).ok_or_else(|| {
+                    (
+                        format!("unknown field {}, expected {}", name, FIELDS),
+                        column,
+                    )
+                })?;
+                let column = self.column();
+                let Some((Token::Op(op), _)) = self.next() else {
+                    return Err((format!("expected an operator after {}", name), column));
+                };
+                let column = self.column();
+                let value = match self.next() {
+                    Some((Token::Word(value) | Token::Quoted(value), _)) => value,
+                    _ => return Err((format!("expected a value after {}", name), column)),
+                };
+                let value = parse_value(&field, op, &value).map_err(|message| (message, column))?;
+                Ok(Expr::Condition(Condition { field, op, value }))
+            }
+            Some((Token::Close, _)) => Err(("unexpected )".to_string(), column)),
+            Some(_) => Err(("expected a field name".to_string(), column)),
+            None => Err(("expected a condition".to_string(), column)),
+        }
+    }
+}
+
+fn parse_field(name: &str) -> Option<Field> {
+    let field = match name.to_lowercase().as_str() {
+        "status" => Field::Status,
+        "text" => Field::Text,
+        "tag" => Field::Tag,
+        "priority" => Field::Priority,
+        "due" => Field::Due,
+        "created" => Field::Created,
+        "completed" => Field::Completed,
+        "list" => Field::List,
+        "id" => Field::Id,
+        _ => Field::Meta(
+            name.strip_prefix("meta.")
+                .filter(|key| !key.is_empty())?
+                .to_string(),
+        ),
+    };
+    Some(field)
+}
+
+impl Field {
+    fn name(&self) -> String {
+        let name = match self {
+            Field::Status => "status",
+            Field::Text => "text",
+            Field::Tag => "tag",
+            Field::Priority => "priority",
+            Field::Due => "due",
+            Field::Created => "created",
+            Field::Completed => "completed",
+            Field::List => "list",
+            Field::Id => "id",
+            Field::Meta(key) => return format!("meta.{}", key),
+        };
+        name.to_string()
+    }
+}
+
+fn parse_value(field: &Field, op: Op, value: &str) -> Result<Value, String> {
+    let ordered = matches!(
+        field,
+        Field::Priority | Field::Due | Field::Created | Field::Completed
+    );
+    let textual = matches!(field, Field::Text | Field::Meta(_));
+    let allowed = match op {
+        Op::Has | Op::Eq | Op::Ne => true,
+        Op::Lt | Op::Le | Op::Gt | Op::Ge => ordered,
+        Op::Matches => textual,
+    };
+    if !allowed {
+        return Err(format!(
+            "{} can't be compared with {}",
+            field.name(),
+            symbol(op)
+        ));
+    }
+
+    let lower = value.to_lowercase();
+    if lower == "none" && !matches!(field, Field::Status | Field::Tag | Field::Id) {
+        if ordered && !matches!(op, Op::Has | Op::Eq | Op::Ne) {
+            return Err("none can't be ordered".to_string());
+        }
+        return Ok(Value::None);
+    }
+
+    match field {
+        Field::Status => match lower.as_str() {
+            "open" => Ok(Value::Completed(false)),
+            "completed" | "done" => Ok(Value::Completed(true)),
+            _ => Err(format!(
+                "{} is no status, expected open or completed",
+                value
+            )),
+        },
+        Field::Priority => match lower.as_str() {
+            "high" => Ok(Value::Priority('A')),
+            "medium" => Ok(Value::Priority('B')),
+            "low" => Ok(Value::Priority('C')),
+            _ if value.len() == 1 && value.chars().all(|c| c.is_ascii_alphabetic()) => Ok(
+                Value::Priority(value.to_ascii_uppercase().chars().next().unwrap()),
+            ),
+            _ => Err(format!(
+                "{} is no priority, expected a letter, high, medium or low",
+                value
+            )),
+        },
+        Field::Due | Field::Created | Field::Completed => {
+            parse_date(&lower).map(Value::Date).ok_or_else(|| {
+                format!(
+                    "{} is no date, expected YYYY-MM-DD, today or days from today like +7d",
+                    value
+                )
+            })
+        }
+        _ if op == Op::Matches => RegexBuilder::new(value)
+            .case_insensitive(true)
+            .build()
+            .map(Value::Regex)
+            .map_err(|err| format!("invalid regular expression: {}", err)),
+        _ => Ok(Value::Text(value.to_string())),
+    }
+}
+
+fn parse_date(value: &str) -> Option<Date> {
+    let days = match value {
+        "today" => 0,
+        "tomorrow" => 1,
+        "yesterday" => -1,
+        _ if value.starts_with(['+', '-']) => {
+            let (index, unit) = value.char_indices().last()?;
+            let number: i64 = value[..index].parse().ok()?;
+            match unit {
+                'd' => number,
+                'w' => number.checked_mul(7)?,
+                _ => return None,
+            }
+        }
+        _ => {
+            return NaiveDate::parse_from_str(value, "%Y-%m-%d")
+                .ok()
+                .map(Date::Absolute)
+        }
+    };
+    Some(Date::Relative(days))
+}
+
+fn symbol(op: Op) -> &'static str {
+    match op {
+        Op::Has => ":",
+        Op::Eq => "=",
+        Op::Ne => "!=",
+        Op::Lt => "<",
+        Op::Le => "<=",
+        Op::Gt => ">",
+        Op::Ge => ">=",
+        Op::Matches => "~",
+    }
+}
+
+fn evaluate(expr: &Expr, todo: &Todo, today: NaiveDate) -> bool {
+    match expr {
+        Expr::And(left, right) => evaluate(left, todo, today) && evaluate(right, todo, today),
+        Expr::Or(left, right) => evaluate(left, todo, today) || evaluate(right, todo, today),
+        Expr::Not(expr) => !evaluate(expr, todo, today),
+        Expr::Condition(condition) => condition.matches(todo, today),
+    }
+}
+
+impl Condition {
+    fn matches(&self, todo: &Todo, today: NaiveDate) -> bool {
+        let op = self.op;
+        match (&self.field, &self.value) {
+            (Field::Status, Value::Completed(completed)) => {
+                (todo.completed == *completed) != (op == Op::Ne)
+            }
+            (Field::Tag, Value::Text(tag)) => {
+                let has = match tag.chars().next() {
+                    Some('+') | Some('@') => todo.text.split_whitespace().any(|word| word == tag),
+                    _ => {
+                        todo.projects().contains(&tag.as_str())
+                            || todo.contexts().contains(&tag.as_str())
+                    }
+                };
+                has != (op == Op::Ne)
+            }
+            (Field::Id, Value::Text(id)) => {
+                let id = id.to_lowercase();
+                let found = match op {
+                    Op::Has => todo.id.to_string().starts_with(&id),
+                    _ => todo.id.to_string() == id,
+                };
+                found != (op == Op::Ne)
+            }
+            (Field::Priority, value) => {
+                // Earlier letters are higher priorities
+                let rank = |priority: char| std::cmp::Reverse(priority);
+                compare(
+                    todo.priority.map(rank),
+                    value,
+                    |value| match value {
+                        Value::Priority(priority) => Some(rank(*priority)),
+                        _ => None,
+                    },
+                    op,
+                )
+            }
+            (Field::Due | Field::Created | Field::Completed, value) => {
+                let date = match self.field {
+                    Field::Due => todo.due(),
+                    Field::Created => todo.created,
+                    _ => todo.completed_at,
+                };
+                compare(
+                    date,
+                    value,
+                    |value| match value {
+                        Value::Date(Date::Absolute(date)) => Some(*date),
+                        // Days beyond the dates chrono knows are before or after all of them
+                        Value::Date(Date::Relative(days)) => Some(if *days < 0 {
+                            today
+                                .checked_sub_days(Days::new(days.unsigned_abs()))
+                                .unwrap_or(NaiveDate::MIN)
+                        } else {
+                            today
+                                .checked_add_days(Days::new(*days as u64))
+                                .unwrap_or(NaiveDate::MAX)
+                        }),
+                        _ => None,
+                    },
+                    op,
+                )
+            }
+            (field, value) => {
+                let text = match field {
+                    Field::Text => Some(todo.text.as_str()),
+                    Field::List => todo.list.as_deref(),
+                    Field::Meta(key) => todo.metadata.get(key).map(String::as_str),
+                    _ => None,
+                };
+                let found = match (text, value) {
+                    (text, Value::None) => text.is_none(),
+                    (Some(text), Value::Regex(regex)) => regex.is_match(text),
+                    (Some(text), Value::Text(value)) if op == Op::Has && *field != Field::List => {
+                        text.to_lowercase().contains(&value.to_lowercase())
+                    }
+                    (Some(text), Value::Text(value)) => text.eq_ignore_ascii_case(value),
+                    _ => false,
+                };
+                found != (op == Op::Ne)
+            }
+        }
+    }
+}
+
+/// Compares an ordered field. Missing values only equal `none` and are never
+/// ordered.
+fn compare<T: Ord>(
+    actual: Option<T>,
+    value: &Value,
+    expected: impl Fn(&Value) -> Option<T>,
+    op: Op,
+) -> bool {
+    let ordering = match (actual, value) {
+        (actual, Value::None) => {
+            return actual.is_none() != (op == Op::Ne);
+        }
+        (Some(actual), value) => expected(value).map(|expected| actual.cmp(&expected)),
+        (None, _) => None,
+    };
+    match op {
+        Op::Has | Op::Eq => ordering == Some(Ordering::Equal),
+        Op::Ne => ordering != Some(Ordering::Equal),
+        Op::Lt => ordering == Some(Ordering::Less),
+        Op::Le => matches!(ordering, Some(Ordering::Less | Ordering::Equal)),
+        Op::Gt => ordering == Some(Ordering::Greater),
+        Op::Ge => matches!(ordering, Some(Ordering::Greater | Ordering::Equal)),
+        Op::Matches => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matches() {
+        let today = NaiveDate::from_ymd_opt(2026, 10, 19).unwrap();
+        let mut deploy = Todo::new("Deploy the app +release @work".to_string());
+        deploy.priority = Some('B');
+        deploy.set_due(NaiveDate::from_ymd_opt(2026, 10, 23));
+        deploy
+            .metadata
+            .insert("estimate".to_string(), "2h".to_string());
+        let mut bug = Todo::new("Fix crash +bug".to_string());
+        bug.list = Some("Work".to_string());
+        let mut done = Todo::new("Deploy hotfix".to_string());
+        done.completed = true;
+        done.priority = Some('A');
+        let todos = [deploy, bug, done];
+
+        let matching = |source: &str| -> Vec<usize> {
+            let filter = Filter::parse(source).unwrap();
+            (0..todos.len())
+                .filter(|&i| filter.matches(&todos[i], today))
+                .collect()
+        };
+        assert_eq!(
+            matching(
+                r#"status:open and (tag:bug or priority>=medium) and due<+7d and text~"deploy""#
+            ),
+            vec![0]
+        );
+        assert_eq!(
+            matching("status:open (tag:bug or priority>=medium)"),
+            vec![0, 1]
+        );
+        assert_eq!(matching("priority>=high"), vec![2]);
+        assert_eq!(matching("priority:none or priority<b"), vec![1]);
+        assert_eq!(matching("not due:none"), vec![0]);
+        assert_eq!(matching("due=2026-10-23 or due>tomorrow"), vec![0]);
+        assert!(matching("due>=+5d").is_empty());
+        assert_eq!(matching("tag:+release tag:@work tag!=bug"), vec![0]);
+        assert_eq!(matching("text:DEPLOY"), vec![0, 2]);
+        assert_eq!(matching("list=work or meta.estimate:2"), vec![0, 1]);
+        assert_eq!(matching("list:none and status:done"), vec![2]);
+        assert_eq!(
+            matching("status:open or status:open and tag:none"),
+            vec![0, 1]
+        );
+        let id = todos[1].id.to_string();
+        assert_eq!(matching(&format!("id:{}", &id[..8])), vec![1]);
+        assert_eq!(matching(&format!("id!={}", id)), vec![0, 2]);
+    }
+
+    #[test]
+    fn test_errors() {
+        let error = |source: &str| Filter::parse(source).unwrap_err().0;
+        assert_eq!(
+            error("status:open and (tag:bug"),
+            "Invalid filter at column 17: ( isn't closed\n  status:open and (tag:bug\n                  ^"
+        );
+        for (source, message) in [
+            ("", "column 1: expected a condition"),
+            ("statu:open", "column 1: unknown field statu"),
+            ("status", "column 7: expected an operator after status"),
+            ("due<", "column 5: expected a value after due"),
+            ("due<soon", "column 5: soon is no date"),
+            ("status<open", "column 8: status can't be compared with <"),
+            ("priority>=urgent", "column 11: urgent is no priority"),
+            ("text~\"(\"", "column 6: invalid regular expression"),
+            ("text:\"open", "column 6: \" isn't closed"),
+            ("tag:bug)", "column 8: unexpected )"),
+            ("tag:bug or", "column 11: expected a condition"),
+            ("tag!bug", "column 4: expected !="),
+            ("due<+é", "column 5: +é is no date"),
+            (
+                "due<+9223372036854775807w",
+                "column 5: +9223372036854775807w is no date",
+            ),
+        ] {
+            let error = error(source);
+            assert!(error.contains(message), "{}: {}", source, error);
+        }
+
+        // Days past the range of dates compare as the first or last date
+        let mut todo = Todo::new("Far".to_string());
+        todo.set_due(NaiveDate::from_ymd_opt(2026, 10, 19));
+        let today = NaiveDate::from_ymd_opt(2026, 10, 19).unwrap();
+        for (source, matches) in [
+            ("due<+99999999999d", true),
+            ("due>-9223372036854775808d", true),
+            ("due>+99999999999d", false),
+        ] {
+            let filter = Filter::parse(source).unwrap();
+            assert_eq!(filter.matches(&todo, today), matches, "{}", source);
+        }
+    }
+}
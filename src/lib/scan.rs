@@ -0,0 +1,329 @@
+//! Finds `TODO`, `FIXME` and `HACK` comments in source trees and keeps TODOs of
+//! them in sync with the code.
+
+use crate::{errors::ApplicationError, todo::Todo};
+use chrono::NaiveDate;
+use ignore::WalkBuilder;
+use regex::Regex;
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+    sync::LazyLock,
+};
+use uuid::Uuid;
+
+/// Metadata key of the file a TODO was found in
+pub const FILE_KEY: &str = "file";
+/// Metadata key of the line a TODO was found in
+pub const LINE_KEY: &str = "line";
+
+/// A marker right after the start of a comment in C-like languages, scripts, SQL,
+/// Lisp, LaTeX or HTML, like `// TODO(name): text` or `# FIXME text`. Matched where
+/// `comment_starts` found a comment may start.
+static MARKER: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"^(?://+|#+|/\*+|\*|<!--|--|;+|%+)\s*(TODO|FIXME|HACK)\b(?:\([^)]*\))?:?\s*(.*?)\s*(?:\*/|-->)?\s*$",
+    )
+    .unwrap()
+});
+
+/// A marker comment found in a file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Marker {
+    pub file: PathBuf,
+    /// Starting at 1
+    pub line: usize,
+    /// `TODO`, `FIXME` or `HACK`
+    pub kind: String,
+    pub text: String,
+}
+
+impl Marker {
+    /// The text of the TODO for the marker.
+    pub fn todo_text(&self) -> String {
+        if self.text.is_empty() {
+            self.kind.clone()
+        } else {
+            format!("{}: {}", self.kind, self.text)
+        }
+    }
+}
+
+/// The markers in the files below `root`. Files ignored by `.gitignore` and hidden
+/// ones are skipped, as are files which aren't text.
+/// The files are absolute, so they are the same however `root` is given.
+pub fn scan(root: &Path) -> Result<Vec<Marker>, ApplicationError> {
+    let Some(root) = root.canonicalize().ok().filter(|root| root.is_dir()) else {
+        return Err(ApplicationError(format!(
+            "{} is no directory",
+            root.display()
+        )));
+    };
+
+    let mut markers = Vec::new();
+    let walk = WalkBuilder::new(root)
+        .require_git(false)
+        .sort_by_file_path(|a, b| a.cmp(b))
+        .build();
+    for entry in walk {
+        let entry = entry.map_err(|err| ApplicationError(err.to_string()))?;
+        if !entry
+            .file_type()
+            .is_some_and(|file_type| file_type.is_file())
+        {
+            continue;
+        }
+        let Ok(content) = std::fs::read_to_string(entry.path()) else {
+            continue;
+        };
+        markers.extend(find_markers(entry.path(), &content));
+    }
+    Ok(markers)
+}
+
+pub fn find_markers(file: &Path, content: &str) -> Vec<Marker> {
+    content
+        .lines()
+        .enumerate()
+        .filter_map(|(i, line)| {
+            let captures = comment_starts(line)
+                .into_iter()
+                .find_map(|start| MARKER.captures(&line[start..]))?;
+            Some(Marker {
+                file: file.to_path_buf(),
+                line: i + 1,
+                kind: captures[1].to_string(),
+                text: captures[2].to_string(),
+            })
+        })
+        .collect()
+}
+
+/// Where comments may start in `line`, outside of string literals. `#`, `;`, `%`
+/// and `--` only start one at the start of the line or after whitespace, as they
+/// are operators in other languages, and `*` only continues a block comment at the
+/// start of the line.
+fn comment_starts(line: &str) -> Vec<usize> {
+    let mut starts = Vec::new();
+    let mut quote = None;
+    let mut escaped = false;
+    let mut previous: Option<char> = None;
+    for (i, c) in line.char_indices() {
+        let rest = &line[i..];
+        match quote {
+            Some(_) if escaped => escaped = false,
+            Some(_) if c == '\\' => escaped = true,
+            Some(quoted) if c == quoted => quote = None,
+            Some(_) => {}
+            None if rest.starts_with("//")
+                || rest.starts_with("/*")
+                || rest.starts_with("<!--") =>
+            {
+                starts.push(i)
+            }
+            None if previous.is_none_or(char::is_whitespace)
+                && (rest.starts_with(['#', ';', '%']) || rest.starts_with("--")) =>
+            {
+                starts.push(i)
+            }
+            None if c == '*' && line[..i].trim().is_empty() => starts.push(i),
+            // A single quote without a closing one is an apostrophe or a lifetime
+            None if c == '"' || (c == '\'' && line[i + 1..].contains('\'')) => quote = Some(c),
+            None => {}
+        }
+        previous = Some(c);
+    }
+    starts
+}
+
+/// What a scan changed in the TODO list.
+#[derive(Debug, Default, PartialEq)]
+pub struct Scanned {
+    pub added: usize,
+    /// Still in the code, but at another line
+    pub moved: usize,
+    /// Their comment is gone
+    pub closed: usize,
+}
+
+fn source(todo: &Todo) -> Option<(PathBuf, usize)> {
+    let file = todo.metadata.get(FILE_KEY)?;
+    let line = todo.metadata.get(LINE_KEY)?.parse().ok()?;
+    Some((PathBuf::from(file), line))
+}
+
+/// Adds a TODO for every new marker found below `root` and completes the TODOs of
+/// markers below it which are gone. Markers are matched by file and text, so ones
+/// whose line changed aren't added again.
+pub fn apply(todos: &mut Vec<Todo>, root: &Path, markers: &[Marker], today: NaiveDate) -> Scanned {
+    // Like the files of the markers
+    let root = root.canonicalize().unwrap_or_else(|_| root.to_path_buf());
+    let mut result = Scanned::default();
+    let mut matched: HashSet<Uuid> = HashSet::new();
+
+    for marker in markers {
+        let text = marker.todo_text();
+        let file = marker.file.display().to_string();
+        // The one closest to the marker's line, if the same comment is there twice
+        let existing = todos
+            .iter_mut()
+            .filter(|todo| {
+                !matched.contains(&todo.id)
+                    && todo.text == text
+                    && todo.metadata.get(FILE_KEY) == Some(&file)
+            })
+            .min_by_key(|todo| {
+                source(todo).map_or(usize::MAX, |(_, line)| line.abs_diff(marker.line))
+            });
+
+        match existing {
+            Some(todo) => {
+                matched.insert(todo.id);
+                if source(todo).map(|(_, line)| line) != Some(marker.line) {
+                    todo.metadata
+                        .insert(LINE_KEY.to_string(), marker.line.to_string());
+                    result.moved += 1;
+                }
+            }
+            None => {
+                let mut todo = Todo::new(text);
+                todo.created = Some(today);
+                todo.metadata.insert(FILE_KEY.to_string(), file);
+                todo.metadata
+                    .insert(LINE_KEY.to_string(), marker.line.to_string());
+                matched.insert(todo.id);
+                todos.push(todo);
+                result.added += 1;
+            }
+        }
+    }
+
+    for todo in todos.iter_mut() {
+        let gone = !todo.completed
+            && !matched.contains(&todo.id)
+            && source(todo).is_some_and(|(file, _)| file.starts_with(&root));
+        if gone {
+            todo.completed = true;
+            todo.completed_at = Some(today);
+            result.closed += 1;
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_markers() {
+        let content = "fn main() {\n\
+                       \x20   // TODO: handle errors\n\
+                       \x20   let todo = 1; # not a marker: TODO\n\
+                       \x20   /* FIXME(anna) leaks memory */\n\
+                       \x20    * HACK\n\
+                       \x20   -- TODOS are no markers\n\
+                       <!-- TODO: fix the layout -->\n\
+                       }\n";
+        let markers = find_markers(Path::new("main.rs"), content);
+        let found: Vec<(usize, String)> = markers
+            .iter()
+            .map(|marker| (marker.line, marker.todo_text()))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                (2, "TODO: handle errors".to_string()),
+                (4, "FIXME: leaks memory".to_string()),
+                (5, "HACK".to_string()),
+                (7, "TODO: fix the layout".to_string()),
+            ]
+        );
+
+        // Comment starts in strings and operators are no comments
+        let content = "println!(\"// TODO: in a string\");\n\
+                       x = 1; TODO no comment\n\
+                       s = 'it # TODO' + \"\\\" # TODO\"\n\
+                       i--; TODO no comment either\n\
+                       echo \"it's\" # TODO: after a string\n\
+                       (defun f () nil) ; FIXME in Lisp\n";
+        let markers = find_markers(Path::new("main.rs"), content);
+        let found: Vec<(usize, String)> = markers
+            .iter()
+            .map(|marker| (marker.line, marker.todo_text()))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                (5, "TODO: after a string".to_string()),
+                (6, "FIXME: in Lisp".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_rescan() {
+        let dir = std::env::temp_dir().join(format!("todo_scan_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("src")).unwrap();
+        std::fs::create_dir_all(dir.join("target")).unwrap();
+        std::fs::write(dir.join(".gitignore"), "target/\n").unwrap();
+        std::fs::write(dir.join("target/gen.rs"), "// TODO: ignored\n").unwrap();
+        std::fs::write(dir.join("src/main.rs"), "// TODO: first\n// TODO: second\n").unwrap();
+        std::fs::write(dir.join("image.png"), [0xff, 0xfe, 0x00]).unwrap();
+        let today = NaiveDate::from_ymd_opt(2026, 10, 19).unwrap();
+
+        let mut todos = vec![Todo::new("unrelated".to_string())];
+        let markers = scan(&dir).unwrap();
+        assert_eq!(markers.len(), 2);
+        let result = apply(&mut todos, &dir, &markers, today);
+        assert_eq!(result.added, 2);
+        assert_eq!(todos[2].metadata[LINE_KEY], "2");
+
+        // Rescanning doesn't add them again, a removed one is closed
+        std::fs::write(
+            dir.join("src/main.rs"),
+            "\n// TODO: second\n// TODO: third\n",
+        )
+        .unwrap();
+        let markers = scan(&dir).unwrap();
+        let result = apply(&mut todos, &dir, &markers, today);
+        assert_eq!(
+            result,
+            Scanned {
+                added: 1,
+                moved: 0,
+                closed: 1
+            }
+        );
+        assert_eq!(todos.len(), 4);
+        assert!(todos[1].completed);
+        assert_eq!(todos[1].completed_at, Some(today));
+        assert!(!todos[0].completed && !todos[2].completed);
+        assert_eq!(
+            todos[2].metadata[FILE_KEY],
+            dir.join("src/main.rs").display().to_string()
+        );
+
+        std::fs::write(
+            dir.join("src/main.rs"),
+            "\n\n// TODO: second\n// TODO: third\n",
+        )
+        .unwrap();
+        let markers = scan(&dir).unwrap();
+        let result = apply(&mut todos, &dir, &markers, today);
+        assert_eq!(result.moved, 2);
+        assert_eq!(todos[2].metadata[LINE_KEY], "3");
+
+        // The same directory given another way
+        let other = dir.join("src/..");
+        let markers = scan(&other).unwrap();
+        assert_eq!(
+            apply(&mut todos, &other, &markers, today),
+            Scanned::default()
+        );
+        assert!(scan(&dir.join("missing")).is_err());
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,165 @@
+use crate::{errors::ApplicationError, todo::Todo};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::{
+    io::ErrorKind,
+    path::{Path, PathBuf},
+};
+
+pub const DEFAULT_DIR: &str = "snapshots";
+
+/// The full TODO list at one point in time.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Snapshot {
+    pub label: String,
+    pub created: DateTime<Utc>,
+    pub todos: Vec<Todo>,
+}
+
+/// Snapshots kept as one JSON file each in a directory.
+pub struct SnapshotStore {
+    dir: PathBuf,
+}
+
+impl SnapshotStore {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    pub fn create(&self, label: &str, todos: &[Todo]) -> Result<Snapshot, ApplicationError> {
+        let snapshot = Snapshot {
+            label: label.to_string(),
+            created: Utc::now(),
+            todos: todos.to_vec(),
+        };
+        std::fs::create_dir_all(&self.dir)?;
+
+        // File names sort by time, the counter orders snapshots of the same millisecond
+        let stem = snapshot.created.format("%Y%m%dT%H%M%S%.3fZ").to_string();
+        let mut counter = 0;
+        let mut path = self.dir.join(format!("{}-{:03}.json", stem, counter));
+        while path.exists() {
+            counter += 1;
+            path = self.dir.join(format!("{}-{:03}.json", stem, counter));
+        }
+        std::fs::write(path, serde_json::to_vec(&snapshot)?)?;
+        Ok(snapshot)
+    }
+
+    /// All snapshots, the oldest first.
+    pub fn list(&self) -> Result<Vec<Snapshot>, ApplicationError> {
+        let entries = match std::fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(err) => return Err(err.into()),
+        };
+
+        let mut paths = Vec::new();
+        for entry in entries {
+            let path = entry?.path();
+            if path.extension().is_some_and(|ext| ext == "json") {
+                paths.push(path);
+            }
+        }
+        paths.sort();
+        paths.iter().map(|path| read_snapshot(path)).collect()
+    }
+}
+
+fn read_snapshot(path: &Path) -> Result<Snapshot, ApplicationError> {
+    serde_json::from_slice(&std::fs::read(path)?)
+        .map_err(|err| ApplicationError(format!("Invalid snapshot {}: {}", path.display(), err)))
+}
+
+/// How the current TODOs differ from a snapshot.
+#[derive(Debug, Default, PartialEq)]
+pub struct SnapshotDiff {
+    /// Only in the current list
+    pub added: Vec<Todo>,
+    /// Only in the snapshot
+    pub removed: Vec<Todo>,
+    /// The version of the snapshot and the current one
+    pub changed: Vec<(Todo, Todo)>,
+}
+
+impl SnapshotDiff {
+    pub fn new(snapshot: &[Todo], current: &[Todo]) -> Self {
+        let find = |todos: &[Todo], todo: &Todo| todos.iter().find(|t| t.id == todo.id).cloned();
+        let mut diff = SnapshotDiff::default();
+        for todo in current {
+            match find(snapshot, todo) {
+                Some(old) if old != *todo => diff.changed.push((old, todo.clone())),
+                Some(_) => {}
+                None => diff.added.push(todo.clone()),
+            }
+        }
+        diff.removed = snapshot
+            .iter()
+            .filter(|todo| find(current, todo).is_none())
+            .cloned()
+            .collect();
+        diff
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+
+    /// The snapshot versions that can be cherry-picked: removed TODOs, then changed ones.
+    pub fn restorable(&self) -> Vec<Todo> {
+        self.removed
+            .iter()
+            .cloned()
+            .chain(self.changed.iter().map(|(old, _)| old.clone()))
+            .collect()
+    }
+}
+
+/// Puts the snapshot version of `todo` back into `todos`, replacing the current one.
+pub fn restore_todo(todos: &mut Vec<Todo>, todo: Todo) {
+    match todos.iter_mut().find(|current| current.id == todo.id) {
+        Some(current) => *current = todo,
+        None => todos.push(todo),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snapshots() {
+        let dir = std::env::temp_dir().join(format!("todo_snapshots_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let store = SnapshotStore::new(&dir);
+        assert!(store.list().unwrap().is_empty());
+
+        let first = Todo::new("first".to_string());
+        let second = Todo::new("second".to_string());
+        store
+            .create("before", &[first.clone(), second.clone()])
+            .unwrap();
+        store.create("after", std::slice::from_ref(&first)).unwrap();
+
+        let snapshots = store.list().unwrap();
+        assert_eq!(snapshots.len(), 2);
+        assert_eq!(snapshots[0].label, "before");
+        assert_eq!(snapshots[1].label, "after");
+
+        let mut edited = first.clone();
+        edited.completed = true;
+        let added = Todo::new("added".to_string());
+        let mut current = vec![edited.clone(), added.clone()];
+        let diff = SnapshotDiff::new(&snapshots[0].todos, &current);
+        assert_eq!(diff.added, vec![added.clone()]);
+        assert_eq!(diff.removed, vec![second.clone()]);
+        assert_eq!(diff.changed, vec![(first.clone(), edited)]);
+        assert_eq!(diff.restorable(), vec![second.clone(), first.clone()]);
+
+        restore_todo(&mut current, first.clone());
+        restore_todo(&mut current, second.clone());
+        assert_eq!(current, vec![first, added, second]);
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,162 @@
+use crate::errors::ApplicationError;
+use argon2::{Algorithm, Argon2, Params, Version};
+use chacha20poly1305::{
+    aead::{array::Array, Aead, Generate, KeyInit, Payload},
+    consts::U16,
+    XChaCha20Poly1305, XNonce,
+};
+
+/// Start of every encrypted data file.
+const MAGIC: &[u8; 8] = b"TODOENC1";
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 24;
+/// Magic, the three Argon2 cost parameters, salt and nonce.
+const HEADER_LEN: usize = MAGIC.len() + 3 * 4 + SALT_LEN + NONCE_LEN;
+
+/// Key derived from a passphrase with Argon2id, along with what it was derived from.
+/// The cost parameters are stored in every file, so they can be raised later on
+/// without breaking existing files.
+pub struct DerivedKey {
+    params: [u32; 3],
+    salt: [u8; SALT_LEN],
+    cipher: XChaCha20Poly1305,
+}
+
+impl DerivedKey {
+    /// Derives a key with a fresh salt and the default cost parameters.
+    pub fn new(passphrase: &str) -> Result<Self, ApplicationError> {
+        let params = Params::default();
+        let salt = Array::<u8, U16>::generate();
+        Self::derive(
+            passphrase,
+            [params.m_cost(), params.t_cost(), params.p_cost()],
+            salt.into(),
+        )
+    }
+
+    fn derive(
+        passphrase: &str,
+        params: [u32; 3],
+        salt: [u8; SALT_LEN],
+    ) -> Result<Self, ApplicationError> {
+        let argon_params = Params::new(params[0], params[1], params[2], Some(32))
+            .map_err(|err| ApplicationError(format!("Invalid key parameters: {}", err)))?;
+        let mut key = [0u8; 32];
+        Argon2::new(Algorithm::Argon2id, Version::V0x13, argon_params)
+            .hash_password_into(passphrase.as_bytes(), &salt, &mut key)
+            .map_err(|err| ApplicationError(format!("Failed to derive key: {}", err)))?;
+
+        let cipher = XChaCha20Poly1305::new(&key.into());
+        Ok(Self {
+            params,
+            salt,
+            cipher,
+        })
+    }
+
+    /// Derives the key for `content` from `passphrase`, reusing `self` if it was
+    /// derived with the same salt and parameters.
+    pub fn for_content(
+        current: Option<Self>,
+        passphrase: &str,
+        content: &[u8],
+    ) -> Result<Self, ApplicationError> {
+        let (params, salt, _) = parse_header(content)?;
+        match current {
+            Some(key) if key.params == params && key.salt == salt => Ok(key),
+            _ => Self::derive(passphrase, params, salt),
+        }
+    }
+
+    pub fn encrypt(&self, plaintext: &[u8]) -> Result<Vec<u8>, ApplicationError> {
+        let nonce = XNonce::generate(); // Must never be reused with the same key
+        let mut content = Vec::with_capacity(HEADER_LEN + plaintext.len() + 16);
+        content.extend_from_slice(MAGIC);
+        for param in self.params {
+            content.extend_from_slice(&param.to_le_bytes());
+        }
+        content.extend_from_slice(&self.salt);
+        content.extend_from_slice(&nonce);
+
+        // The header is authenticated as well, so the parameters can't be tampered with
+        let payload = Payload {
+            msg: plaintext,
+            aad: &content,
+        };
+        let ciphertext = self
+            .cipher
+            .encrypt(&nonce, payload)
+            .map_err(|_| ApplicationError("Failed to encrypt data".to_string()))?;
+        content.extend_from_slice(&ciphertext);
+        Ok(content)
+    }
+
+    /// Decrypts `content`, returning `None` if the key doesn't match, i.e. the
+    /// passphrase was wrong, or the content was modified.
+    pub fn decrypt(&self, content: &[u8]) -> Result<Option<Vec<u8>>, ApplicationError> {
+        let (_, _, nonce) = parse_header(content)?;
+        let payload = Payload {
+            msg: &content[HEADER_LEN..],
+            aad: &content[..HEADER_LEN],
+        };
+        Ok(self.cipher.decrypt(&XNonce::from(nonce), payload).ok())
+    }
+}
+
+pub fn is_encrypted(content: &[u8]) -> bool {
+    content.starts_with(MAGIC)
+}
+
+type Header = ([u32; 3], [u8; SALT_LEN], [u8; NONCE_LEN]);
+
+fn parse_header(content: &[u8]) -> Result<Header, ApplicationError> {
+    if !is_encrypted(content) || content.len() < HEADER_LEN {
+        return Err(ApplicationError(
+            "The data file is not encrypted or truncated".to_string(),
+        ));
+    }
+
+    let mut params = [0u32; 3];
+    for (i, param) in params.iter_mut().enumerate() {
+        let start = MAGIC.len() + i * 4;
+        *param = u32::from_le_bytes(content[start..start + 4].try_into().unwrap_or_default());
+    }
+    let salt_start = MAGIC.len() + 3 * 4;
+    let nonce_start = salt_start + SALT_LEN;
+    let salt = content[salt_start..nonce_start]
+        .try_into()
+        .unwrap_or_default();
+    let nonce = content[nonce_start..HEADER_LEN]
+        .try_into()
+        .unwrap_or([0; NONCE_LEN]);
+    Ok((params, salt, nonce))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Cheap parameters, the defaults make the tests slow.
+    fn test_key(passphrase: &str) -> DerivedKey {
+        DerivedKey::derive(passphrase, [256, 1, 1], [7; SALT_LEN]).unwrap()
+    }
+
+    #[test]
+    fn test_encrypt_decrypt() {
+        let key = test_key("secret");
+        let content = key.encrypt(b"[1, 2, 3]").unwrap();
+        assert!(is_encrypted(&content));
+        assert_eq!(key.decrypt(&content).unwrap().unwrap(), b"[1, 2, 3]");
+
+        let reused = DerivedKey::for_content(Some(key), "ignored", &content).unwrap();
+        assert_eq!(reused.decrypt(&content).unwrap().unwrap(), b"[1, 2, 3]");
+
+        let wrong = DerivedKey::for_content(None, "wrong", &content).unwrap();
+        assert_eq!(wrong.decrypt(&content).unwrap(), None);
+
+        let mut tampered = content.clone();
+        *tampered.last_mut().unwrap() ^= 1;
+        assert_eq!(test_key("secret").decrypt(&tampered).unwrap(), None);
+        assert!(test_key("secret").decrypt(b"TODOENC1").is_err());
+    }
+}
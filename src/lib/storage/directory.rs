@@ -0,0 +1,141 @@
+use super::{not_found, read_optional, Storage};
+use crate::{errors::ApplicationError, todo::Todo};
+use std::{collections::HashSet, io::ErrorKind, path::PathBuf};
+use uuid::Uuid;
+
+const INDEX_FILE: &str = "index.json";
+
+/// Stores every TODO in its own `<id>.json` file inside a directory, so changing one
+/// TODO only touches one file. The order of the list is kept in `index.json`.
+pub struct DirectoryStorage {
+    path: PathBuf,
+}
+
+impl DirectoryStorage {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    fn todo_path(&self, id: Uuid) -> PathBuf {
+        self.path.join(format!("{}.json", id))
+    }
+
+    fn read_index(&self) -> Result<Vec<Uuid>, ApplicationError> {
+        match read_optional(&self.path.join(INDEX_FILE))? {
+            Some(content) => Ok(serde_json::from_slice(&content)?),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    fn write_index(&self, ids: &[Uuid]) -> Result<(), ApplicationError> {
+        let path = self.path.join(INDEX_FILE);
+        let json = serde_json::to_vec(ids)?;
+        if read_optional(&path)?.as_ref() != Some(&json) {
+            std::fs::write(path, json)?;
+        }
+        Ok(())
+    }
+
+    /// Ids of all TODO files in the directory, sorted by file name.
+    fn stored_ids(&self) -> Result<Vec<Uuid>, ApplicationError> {
+        let entries = match std::fs::read_dir(&self.path) {
+            Ok(entries) => entries,
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(err) => return Err(err.into()),
+        };
+
+        let mut ids = Vec::new();
+        for entry in entries {
+            let path = entry?.path();
+            if path.extension().is_some_and(|ext| ext == "json") {
+                if let Some(Ok(id)) = path
+                    .file_stem()
+                    .and_then(|stem| stem.to_str())
+                    .map(Uuid::parse_str)
+                {
+                    ids.push(id);
+                }
+            }
+        }
+        ids.sort();
+        Ok(ids)
+    }
+
+    fn write_todo(&self, todo: &Todo) -> Result<(), ApplicationError> {
+        let path = self.todo_path(todo.id);
+        let json = serde_json::to_vec(todo)?;
+        // Leave unchanged files alone, so their modification time stays meaningful
+        if read_optional(&path)?.as_ref() != Some(&json) {
+            std::fs::write(path, json)?;
+        }
+        Ok(())
+    }
+}
+
+impl Storage for DirectoryStorage {
+    fn load(&mut self) -> Result<Vec<Todo>, ApplicationError> {
+        let stored = self.stored_ids()?;
+        let mut ids = self.read_index()?;
+        ids.retain(|id| stored.contains(id));
+        // Files added without updating the index, e.g. copied over from another machine
+        ids.extend(
+            stored
+                .iter()
+                .filter(|id| !ids.contains(id))
+                .collect::<Vec<_>>(),
+        );
+
+        let mut todos = Vec::with_capacity(ids.len());
+        for id in ids {
+            let content = std::fs::read(self.todo_path(id))?;
+            todos.push(serde_json::from_slice(&content)?);
+        }
+        Ok(todos)
+    }
+
+    fn save(&mut self, todos: &[Todo]) -> Result<(), ApplicationError> {
+        std::fs::create_dir_all(&self.path)?;
+        for todo in todos {
+            self.write_todo(todo)?;
+        }
+
+        let ids: Vec<Uuid> = todos.iter().map(|todo| todo.id).collect();
+        let keep: HashSet<&Uuid> = ids.iter().collect();
+        for id in self.stored_ids()? {
+            if !keep.contains(&id) {
+                std::fs::remove_file(self.todo_path(id))?;
+            }
+        }
+        self.write_index(&ids)
+    }
+
+    fn insert(&mut self, todo: &Todo) -> Result<(), ApplicationError> {
+        std::fs::create_dir_all(&self.path)?;
+        self.write_todo(todo)?;
+
+        let mut ids = self.read_index()?;
+        if !ids.contains(&todo.id) {
+            ids.push(todo.id);
+        }
+        self.write_index(&ids)
+    }
+
+    fn update(&mut self, todo: &Todo) -> Result<(), ApplicationError> {
+        if !self.todo_path(todo.id).exists() {
+            return Err(not_found(todo.id));
+        }
+        self.write_todo(todo)
+    }
+
+    fn remove(&mut self, id: Uuid) -> Result<(), ApplicationError> {
+        match std::fs::remove_file(self.todo_path(id)) {
+            Ok(()) => {}
+            Err(err) if err.kind() == ErrorKind::NotFound => return Err(not_found(id)),
+            Err(err) => return Err(err.into()),
+        }
+
+        let mut ids = self.read_index()?;
+        ids.retain(|stored| *stored != id);
+        self.write_index(&ids)
+    }
+}
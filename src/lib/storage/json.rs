@@ -0,0 +1,210 @@
+use super::{content_hash, crypto, crypto::DerivedKey, read_optional, schema, Storage};
+use crate::{errors::ApplicationError, todo::Todo};
+use serde_json::Value;
+use std::{
+    io::{stderr, Write},
+    path::{Path, PathBuf},
+};
+
+/// Stores all TODOs in a single JSON file, wrapped in a versioned envelope and
+/// optionally encrypted with a key derived from a passphrase.
+pub struct JsonFileStorage {
+    path: PathBuf,
+    /// Set if the file is encrypted
+    passphrase: Option<String>,
+    /// Derived from `passphrase`, cached as deriving is slow on purpose
+    key: Option<DerivedKey>,
+    /// Write indented JSON, one line per field, for readable diffs
+    pretty: bool,
+    /// Hashes of the file and its plaintext as last loaded or saved, to skip saving
+    /// the same data again
+    stored: Option<(u64, u64)>,
+}
+
+impl JsonFileStorage {
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            passphrase: None,
+            key: None,
+            pretty: false,
+            stored: None,
+        }
+    }
+
+    /// Writes the file pretty-printed from now on.
+    pub fn pretty(mut self, pretty: bool) -> Self {
+        self.pretty = pretty;
+        self
+    }
+
+    /// Where the file is backed up before migrating it from `version`.
+    pub fn backup_path(&self, version: u64) -> PathBuf {
+        let mut name = self.path.as_os_str().to_owned();
+        name.push(format!(".v{}.bak", version));
+        PathBuf::from(name)
+    }
+
+    /// Asks for the passphrase until it decrypts the file, at most three times.
+    /// Without an encrypted file yet, the new passphrase has to be entered twice.
+    /// The prompts go to stderr, so they don't mix with the output of commands.
+    pub fn unlock<F>(&mut self, mut get_input: F) -> Result<(), ApplicationError>
+    where
+        F: FnMut() -> Result<String, std::io::Error>,
+    {
+        let content = read_optional(&self.path)?;
+        let Some(content) = content.filter(|content| crypto::is_encrypted(content)) else {
+            eprintln!("The data file will be encrypted.");
+            self.passphrase = Some(read_new_passphrase(&mut get_input)?);
+            return Ok(());
+        };
+
+        for _ in 0..3 {
+            print_input_label("Passphrase: ");
+            let passphrase = get_input()?;
+            let key = DerivedKey::for_content(self.key.take(), &passphrase, &content)?;
+            if key.decrypt(&content)?.is_some() {
+                self.passphrase = Some(passphrase);
+                self.key = Some(key);
+                return Ok(());
+            }
+            eprintln!("Wrong passphrase.");
+        }
+        Err(ApplicationError("Wrong passphrase".to_string()))
+    }
+
+    fn decrypt(&mut self, content: Vec<u8>) -> Result<Vec<u8>, ApplicationError> {
+        if !crypto::is_encrypted(&content) {
+            return Ok(content);
+        }
+
+        let passphrase = self.passphrase.as_ref().ok_or_else(|| {
+            ApplicationError(
+                "The data file is encrypted, set \"encrypted\": true in the config".to_string(),
+            )
+        })?;
+        let key = DerivedKey::for_content(self.key.take(), passphrase, &content)?;
+        let plaintext = key.decrypt(&content)?.ok_or_else(|| {
+            ApplicationError("Wrong passphrase or the data file was modified".to_string())
+        })?;
+        self.key = Some(key);
+        Ok(plaintext)
+    }
+}
+
+impl Storage for JsonFileStorage {
+    fn load(&mut self) -> Result<Vec<Todo>, ApplicationError> {
+        let Some(content) = read_optional(&self.path)? else {
+            return Ok(Vec::new());
+        };
+
+        let file_hash = content_hash(&content);
+        let plaintext = self.decrypt(content)?;
+        self.stored = Some((file_hash, content_hash(&plaintext)));
+        let data: Value = serde_json::from_slice(&plaintext)?;
+        let version = schema::version_of(&data)?;
+        if version < schema::CURRENT_VERSION {
+            backup(&self.path, &self.backup_path(version))?;
+        }
+        Ok(serde_json::from_value(schema::migrate(data)?)?)
+    }
+
+    fn load_content(&mut self, content: &[u8]) -> Result<Vec<Todo>, ApplicationError> {
+        let data: Value = serde_json::from_slice(&self.decrypt(content.to_vec())?)?;
+        Ok(serde_json::from_value(schema::migrate(data)?)?)
+    }
+
+    fn load_raw(&mut self) -> Result<Option<Vec<u8>>, ApplicationError> {
+        read_optional(&self.path)?
+            .map(|content| self.decrypt(content))
+            .transpose()
+    }
+
+    fn save(&mut self, todos: &[Todo]) -> Result<(), ApplicationError> {
+        let data = schema::envelope(todos);
+        let mut content = if self.pretty {
+            let mut content = serde_json::to_vec_pretty(&data)?;
+            content.push(b'\n');
+            content
+        } else {
+            serde_json::to_vec(&data)?
+        };
+
+        // Leave the file alone if it still has the same data, so the modification time,
+        // sync clients and git only see real changes
+        let plaintext_hash = content_hash(&content);
+        if let Some((file_hash, stored_hash)) = self.stored {
+            if stored_hash == plaintext_hash
+                && read_optional(&self.path)?.is_some_and(|file| content_hash(&file) == file_hash)
+            {
+                return Ok(());
+            }
+        }
+
+        if let Some(passphrase) = &self.passphrase {
+            if self.key.is_none() {
+                self.key = Some(DerivedKey::new(passphrase)?);
+            }
+            if let Some(key) = &self.key {
+                content = key.encrypt(&content)?;
+            }
+        }
+
+        // Write a copy and swap it in, so a crash can't leave a half written file
+        let mut tmp_path = self.path.as_os_str().to_owned();
+        tmp_path.push(".tmp");
+        std::fs::write(&tmp_path, &content)?;
+        std::fs::rename(&tmp_path, &self.path)?;
+        self.stored = Some((content_hash(&content), plaintext_hash));
+        Ok(())
+    }
+
+    fn is_encrypted(&self) -> bool {
+        self.passphrase.is_some()
+    }
+
+    fn change_passphrase(&mut self, passphrase: &str) -> Result<(), ApplicationError> {
+        if self.passphrase.is_none() {
+            return Err(ApplicationError(
+                "Encryption is not enabled in the config".to_string(),
+            ));
+        }
+        let todos = self.load()?;
+        self.passphrase = Some(passphrase.to_string());
+        self.key = Some(DerivedKey::new(passphrase)?);
+        self.stored = None; // The data is the same, but has to be encrypted again
+        self.save(&todos)
+    }
+}
+
+/// Asks for a new, non-empty passphrase twice.
+pub fn read_new_passphrase<F>(get_input: &mut F) -> Result<String, ApplicationError>
+where
+    F: FnMut() -> Result<String, std::io::Error>,
+{
+    print_input_label("New passphrase: ");
+    let passphrase = get_input()?;
+    if passphrase.is_empty() {
+        return Err(ApplicationError(
+            "The passphrase must not be empty".to_string(),
+        ));
+    }
+    print_input_label("Repeat the passphrase: ");
+    if get_input()? != passphrase {
+        return Err(ApplicationError("The passphrases don't match".to_string()));
+    }
+    Ok(passphrase)
+}
+
+fn print_input_label(label: &str) {
+    eprint!("{label}");
+    let _ = stderr().flush();
+}
+
+/// Keeps the file as it was before the first migration, later loads don't touch it.
+fn backup(path: &Path, backup_path: &Path) -> Result<(), ApplicationError> {
+    if !backup_path.exists() {
+        std::fs::copy(path, backup_path)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,242 @@
+use super::{apply_change, diff, Change, Storage};
+use crate::{errors::ApplicationError, todo::Todo};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::OpenOptions,
+    io::{ErrorKind, Write},
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+const LOG_FILE: &str = "events.jsonl";
+const SNAPSHOT_FILE: &str = "snapshot.json";
+
+/// One line of the event log.
+#[derive(Serialize, Deserialize)]
+struct Event {
+    seq: u64,
+    /// Unix time in seconds
+    at: u64,
+    #[serde(flatten)]
+    change: Change,
+}
+
+/// The state up to `seq`, written by compaction so the log can start over.
+#[derive(Serialize, Deserialize, Default)]
+struct Snapshot {
+    seq: u64,
+    todos: Vec<Todo>,
+}
+
+struct State {
+    todos: Vec<Todo>,
+    seq: u64,
+    /// Events in the log since the last snapshot
+    events: usize,
+    /// Length of the log up to the last complete line
+    valid_len: u64,
+}
+
+/// Appends every change as a line to `events.jsonl` in a directory instead of
+/// rewriting the whole list. The state is rebuilt by replaying the log on top of
+/// `snapshot.json`, which is rewritten every `compact_after` events.
+pub struct EventLogStorage {
+    path: PathBuf,
+    compact_after: usize,
+    state: Option<State>,
+}
+
+impl EventLogStorage {
+    pub fn new(path: PathBuf, compact_after: usize) -> Self {
+        Self {
+            path,
+            compact_after,
+            state: None,
+        }
+    }
+
+    fn read_state(&self) -> Result<State, ApplicationError> {
+        let snapshot: Snapshot = match std::fs::read(self.path.join(SNAPSHOT_FILE)) {
+            Ok(content) => serde_json::from_slice(&content)?,
+            Err(err) if err.kind() == ErrorKind::NotFound => Snapshot::default(),
+            Err(err) => return Err(err.into()),
+        };
+        let log = match std::fs::read(self.path.join(LOG_FILE)) {
+            Ok(content) => content,
+            Err(err) if err.kind() == ErrorKind::NotFound => Vec::new(),
+            Err(err) => return Err(err.into()),
+        };
+
+        let mut state = State {
+            todos: snapshot.todos,
+            seq: snapshot.seq,
+            events: 0,
+            valid_len: 0,
+        };
+        // A last line without newline is what a crash in the middle of a write leaves
+        // behind. It is ignored here and cut off before the next append.
+        for (i, line) in log.split_inclusive(|b| *b == b'\n').enumerate() {
+            if !line.ends_with(b"\n") {
+                break;
+            }
+            state.valid_len += line.len() as u64;
+
+            let event: Event = serde_json::from_slice(line).map_err(|err| {
+                ApplicationError(format!("Corrupt event log, line {}: {}", i + 1, err))
+            })?;
+            state.events += 1;
+            // Already contained in the snapshot if compaction was interrupted
+            if event.seq > state.seq {
+                apply_change(&mut state.todos, event.change);
+                state.seq = event.seq;
+            }
+        }
+        Ok(state)
+    }
+
+    fn append(&mut self, changes: Vec<Change>) -> Result<(), ApplicationError> {
+        if changes.is_empty() {
+            return Ok(());
+        }
+        let mut state = match self.state.take() {
+            Some(state) => state,
+            None => self.read_state()?,
+        };
+
+        std::fs::create_dir_all(&self.path)?;
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.path.join(LOG_FILE))?;
+        if file.metadata()?.len() > state.valid_len {
+            file.set_len(state.valid_len)?;
+        }
+
+        let at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |time| time.as_secs());
+        let mut lines = Vec::new();
+        for change in changes {
+            state.seq += 1;
+            let event = Event {
+                seq: state.seq,
+                at,
+                change,
+            };
+            serde_json::to_writer(&mut lines, &event)?;
+            lines.push(b'\n');
+            apply_change(&mut state.todos, event.change);
+            state.events += 1;
+        }
+        file.write_all(&lines)?;
+        file.sync_data()?;
+        state.valid_len += lines.len() as u64;
+
+        if state.events >= self.compact_after {
+            self.compact(&mut state)?;
+        }
+        self.state = Some(state);
+        Ok(())
+    }
+
+    /// Writes the state to the snapshot and empties the log.
+    fn compact(&self, state: &mut State) -> Result<(), ApplicationError> {
+        let snapshot = Snapshot {
+            seq: state.seq,
+            todos: state.todos.clone(),
+        };
+        // Replace the snapshot atomically, a crash before the log is truncated is
+        // harmless as the events are skipped by their sequence number
+        let tmp_path = self.path.join(format!("{}.tmp", SNAPSHOT_FILE));
+        std::fs::write(&tmp_path, serde_json::to_vec(&snapshot)?)?;
+        std::fs::rename(tmp_path, self.path.join(SNAPSHOT_FILE))?;
+        OpenOptions::new()
+            .write(true)
+            .open(self.path.join(LOG_FILE))?
+            .set_len(0)?;
+
+        state.events = 0;
+        state.valid_len = 0;
+        Ok(())
+    }
+}
+
+impl Storage for EventLogStorage {
+    fn load(&mut self) -> Result<Vec<Todo>, ApplicationError> {
+        let state = self.read_state()?;
+        let todos = state.todos.clone();
+        self.state = Some(state);
+        Ok(todos)
+    }
+
+    fn save(&mut self, todos: &[Todo]) -> Result<(), ApplicationError> {
+        let current = match &self.state {
+            Some(state) => state.todos.clone(),
+            None => self.load()?,
+        };
+        self.append(diff(&current, todos))
+    }
+
+    fn record(&mut self, changes: &[Change]) -> Result<bool, ApplicationError> {
+        self.append(changes.to_vec())?;
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::tests::temp_path;
+
+    #[test]
+    fn test_compaction() {
+        let dir = temp_path("log_compaction");
+        let mut storage = EventLogStorage::new(dir.clone(), 3);
+
+        let mut todos = Vec::new();
+        for text in ["first", "second", "third", "fourth"] {
+            let todo = Todo::new(text.to_string());
+            storage.insert(&todo).unwrap();
+            todos.push(todo);
+        }
+
+        let log = std::fs::read_to_string(dir.join(LOG_FILE)).unwrap();
+        assert_eq!(log.lines().count(), 1);
+        assert!(dir.join(SNAPSHOT_FILE).exists());
+        assert_eq!(EventLogStorage::new(dir.clone(), 3).load().unwrap(), todos);
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_torn_last_line_is_ignored() {
+        let dir = temp_path("log_torn");
+        let mut storage = EventLogStorage::new(dir.clone(), 100);
+        let first = Todo::new("first".to_string());
+        storage.insert(&first).unwrap();
+
+        let mut file = OpenOptions::new()
+            .append(true)
+            .open(dir.join(LOG_FILE))
+            .unwrap();
+        file.write_all(br#"{"seq":2,"at":0,"op":"ins"#).unwrap();
+
+        let mut storage = EventLogStorage::new(dir.clone(), 100);
+        assert_eq!(storage.load().unwrap(), vec![first.clone()]);
+
+        let second = Todo::new("second".to_string());
+        storage.insert(&second).unwrap();
+        let log = std::fs::read_to_string(dir.join(LOG_FILE)).unwrap();
+        assert_eq!(log.lines().count(), 2);
+        assert_eq!(
+            EventLogStorage::new(dir.clone(), 100).load().unwrap(),
+            vec![first, second]
+        );
+
+        // Broken lines before the end are corruption and not ignored
+        std::fs::write(dir.join(LOG_FILE), "garbage\n").unwrap();
+        assert!(EventLogStorage::new(dir.clone(), 100).load().is_err());
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
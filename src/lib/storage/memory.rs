@@ -0,0 +1,25 @@
+use super::Storage;
+use crate::{errors::ApplicationError, todo::Todo};
+
+/// Keeps the TODOs in memory only, nothing survives the process.
+#[derive(Default)]
+pub struct MemoryStorage {
+    todos: Vec<Todo>,
+}
+
+impl MemoryStorage {
+    pub fn new(todos: Vec<Todo>) -> Self {
+        Self { todos }
+    }
+}
+
+impl Storage for MemoryStorage {
+    fn load(&mut self) -> Result<Vec<Todo>, ApplicationError> {
+        Ok(self.todos.clone())
+    }
+
+    fn save(&mut self, todos: &[Todo]) -> Result<(), ApplicationError> {
+        self.todos = todos.to_vec();
+        Ok(())
+    }
+}
@@ -0,0 +1,504 @@
+use crate::{errors::ApplicationError, get_secret_input, todo::Todo};
+use serde::{Deserialize, Serialize};
+use std::{
+    hash::{DefaultHasher, Hash, Hasher},
+    io::ErrorKind,
+    path::{Path, PathBuf},
+};
+use uuid::Uuid;
+
+pub mod crypto;
+pub mod directory;
+pub mod json;
+pub mod log;
+pub mod memory;
+pub mod schema;
+
+pub use directory::DirectoryStorage;
+pub use json::JsonFileStorage;
+pub use log::EventLogStorage;
+pub use memory::MemoryStorage;
+
+/// A single modification of the TODO list, as done by the actions.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "op", rename_all = "lowercase")]
+pub enum Change {
+    /// Appends a new TODO
+    Insert {
+        todo: Todo,
+    },
+    /// Replaces the TODO with the same id
+    Update {
+        todo: Todo,
+    },
+    Remove {
+        id: Uuid,
+    },
+    /// Puts the TODOs in the order of `ids`
+    Reorder {
+        ids: Vec<Uuid>,
+    },
+}
+
+/// The changes turning `before` into `after`.
+pub fn diff(before: &[Todo], after: &[Todo]) -> Vec<Change> {
+    let mut changes = Vec::new();
+    for todo in before {
+        if !after.iter().any(|other| other.id == todo.id) {
+            changes.push(Change::Remove { id: todo.id });
+        }
+    }
+    for todo in after {
+        match before.iter().find(|other| other.id == todo.id) {
+            Some(other) if other == todo => {}
+            Some(_) => changes.push(Change::Update { todo: todo.clone() }),
+            None => changes.push(Change::Insert { todo: todo.clone() }),
+        }
+    }
+
+    // Deleting swaps the last TODO into the gap, which inserts alone don't reproduce
+    let mut result = before.to_vec();
+    for change in &changes {
+        apply_change(&mut result, change.clone());
+    }
+    if result
+        .iter()
+        .map(|todo| todo.id)
+        .ne(after.iter().map(|todo| todo.id))
+    {
+        changes.push(Change::Reorder {
+            ids: after.iter().map(|todo| todo.id).collect(),
+        });
+    }
+    changes
+}
+
+pub fn apply_change(todos: &mut Vec<Todo>, change: Change) {
+    match change {
+        // Replaying an insert of a stored TODO, e.g. recorded twice, only updates it
+        Change::Insert { todo } => match todos.iter_mut().find(|stored| stored.id == todo.id) {
+            Some(stored) => *stored = todo,
+            None => todos.push(todo),
+        },
+        Change::Update { todo } => {
+            if let Some(stored) = todos.iter_mut().find(|stored| stored.id == todo.id) {
+                *stored = todo;
+            }
+        }
+        Change::Remove { id } => todos.retain(|todo| todo.id != id),
+        Change::Reorder { ids } => {
+            // TODOs missing in `ids` keep their relative order at the end
+            todos.sort_by_key(|todo| {
+                ids.iter()
+                    .position(|id| *id == todo.id)
+                    .unwrap_or(usize::MAX)
+            });
+        }
+    }
+}
+
+/// Persistence of the TODO list.
+///
+/// Backends only have to implement `load` and `save`, the incremental operations
+/// fall back to rewriting everything and can be overridden where a backend can do better.
+pub trait Storage {
+    /// Loads all stored TODOs, in their order.
+    fn load(&mut self) -> Result<Vec<Todo>, ApplicationError>;
+
+    /// Replaces the stored TODOs with `todos`.
+    fn save(&mut self, todos: &[Todo]) -> Result<(), ApplicationError>;
+
+    /// Called with the changes of every action. Backends persisting them right away
+    /// return `true`, the others wait for `save`.
+    fn record(&mut self, _changes: &[Change]) -> Result<bool, ApplicationError> {
+        Ok(false)
+    }
+
+    fn insert(&mut self, todo: &Todo) -> Result<(), ApplicationError> {
+        let mut todos = self.load()?;
+        todos.push(todo.clone());
+        self.save(&todos)
+    }
+
+    fn update(&mut self, todo: &Todo) -> Result<(), ApplicationError> {
+        let mut todos = self.load()?;
+        match todos.iter_mut().find(|stored| stored.id == todo.id) {
+            Some(stored) => *stored = todo.clone(),
+            None => return Err(not_found(todo.id)),
+        }
+        self.save(&todos)
+    }
+
+    fn remove(&mut self, id: Uuid) -> Result<(), ApplicationError> {
+        let mut todos = self.load()?;
+        let len = todos.len();
+        todos.retain(|todo| todo.id != id);
+        if todos.len() == len {
+            return Err(not_found(id));
+        }
+        self.save(&todos)
+    }
+
+    /// Loads the TODOs from data written by this backend at another time, e.g. an
+    /// older version from git. Only backends keeping everything in one file can.
+    fn load_content(&mut self, _content: &[u8]) -> Result<Vec<Todo>, ApplicationError> {
+        Err(ApplicationError(
+            "The storage backend doesn't keep its data in a single file".to_string(),
+        ))
+    }
+
+    /// The content of the single data file, decrypted. `None` if the backend has no
+    /// such file or it doesn't exist yet.
+    fn load_raw(&mut self) -> Result<Option<Vec<u8>>, ApplicationError> {
+        Ok(None)
+    }
+
+    /// Whether the data is encrypted, so copies of it mustn't be written in plaintext.
+    fn is_encrypted(&self) -> bool {
+        false
+    }
+
+    /// Re-encrypts the data with a key derived from `passphrase`.
+    fn change_passphrase(&mut self, _passphrase: &str) -> Result<(), ApplicationError> {
+        Err(ApplicationError(
+            "The storage backend doesn't support encryption".to_string(),
+        ))
+    }
+}
+
+fn not_found(id: Uuid) -> ApplicationError {
+    ApplicationError(format!("TODO {} is not stored", id))
+}
+
+/// Hash to tell whether content changed. Only comparable within one run.
+fn content_hash(content: &[u8]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    content.hash(&mut hasher);
+    hasher.finish()
+}
+
+fn read_optional(path: &Path) -> Result<Option<Vec<u8>>, ApplicationError> {
+    match std::fs::read(path) {
+        Ok(content) => Ok(Some(content)),
+        Err(err) if err.kind() == ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// The `storage` section of the config file, selecting the backend.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "backend", rename_all = "lowercase")]
+pub enum StorageConfig {
+    /// All TODOs in a single JSON file, encrypted with a passphrase if `encrypted` is set
+    /// and pretty-printed if `pretty` is set.
+    Json {
+        path: PathBuf,
+        #[serde(default)]
+        encrypted: bool,
+        #[serde(default)]
+        pretty: bool,
+    },
+    /// One JSON file per TODO in a directory.
+    Directory { path: PathBuf },
+    /// An append-only log of changes in a directory, compacted into a snapshot
+    /// every `compact_after` changes.
+    Log {
+        path: PathBuf,
+        #[serde(default = "default_compact_after")]
+        compact_after: usize,
+    },
+    /// Nothing is persisted, for trying things out and tests.
+    Memory,
+}
+
+impl Default for StorageConfig {
+    fn default() -> Self {
+        StorageConfig::Json {
+            path: PathBuf::from("todos.json"),
+            encrypted: false,
+            pretty: false,
+        }
+    }
+}
+
+fn default_compact_after() -> usize {
+    500
+}
+
+impl StorageConfig {
+    /// The file or directory holding the data, if the backend has one.
+    pub fn path(&self) -> Option<&Path> {
+        match self {
+            StorageConfig::Json { path, .. }
+            | StorageConfig::Directory { path }
+            | StorageConfig::Log { path, .. } => Some(path),
+            StorageConfig::Memory => None,
+        }
+    }
+
+    /// Whether the config asks for the data to be encrypted.
+    pub fn is_encrypted(&self) -> bool {
+        matches!(
+            self,
+            StorageConfig::Json {
+                encrypted: true,
+                ..
+            }
+        )
+    }
+
+    /// Opens the backend, asking for the passphrase if the data is encrypted.
+    pub fn open(&self) -> Result<Box<dyn Storage>, ApplicationError> {
+        Ok(match self {
+            StorageConfig::Json {
+                path,
+                encrypted,
+                pretty,
+            } => {
+                let mut storage = JsonFileStorage::new(path.clone()).pretty(*pretty);
+                if *encrypted {
+                    storage.unlock(get_secret_input)?;
+                }
+                Box::new(storage)
+            }
+            StorageConfig::Directory { path } => Box::new(DirectoryStorage::new(path.clone())),
+            StorageConfig::Log {
+                path,
+                compact_after,
+            } => Box::new(EventLogStorage::new(path.clone(), *compact_after)),
+            StorageConfig::Memory => Box::new(MemoryStorage::default()),
+        })
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// A fresh, empty directory below the system temp dir.
+    pub(crate) fn temp_path(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("todo_{}_{}", name, Uuid::new_v4()));
+        std::fs::create_dir_all(&path).unwrap();
+        path
+    }
+
+    fn todo(text: &str) -> Todo {
+        Todo::new(text.to_string())
+    }
+
+    fn check_backend(storage: &mut dyn Storage) {
+        assert_eq!(storage.load().unwrap(), vec![]);
+
+        let mut todos = vec![todo("first"), todo("second"), todo("third")];
+        storage.save(&todos).unwrap();
+        assert_eq!(storage.load().unwrap(), todos);
+
+        let fourth = todo("fourth");
+        storage.insert(&fourth).unwrap();
+        todos.push(fourth);
+        assert_eq!(storage.load().unwrap(), todos);
+
+        todos[1].completed = true;
+        storage.update(&todos[1]).unwrap();
+        assert_eq!(storage.load().unwrap(), todos);
+
+        let removed = todos.remove(0);
+        storage.remove(removed.id).unwrap();
+        assert_eq!(storage.load().unwrap(), todos);
+
+        assert!(storage.remove(removed.id).is_err());
+        assert!(storage.update(&removed).is_err());
+
+        todos.swap(0, 2);
+        todos.pop();
+        storage.save(&todos).unwrap();
+        assert_eq!(storage.load().unwrap(), todos);
+    }
+
+    #[test]
+    fn test_diff() {
+        let first = todo("first");
+        let second = todo("second");
+        let third = todo("third");
+        let before = vec![first.clone(), second.clone(), third.clone()];
+
+        let mut edited = first.clone();
+        edited.completed = true;
+        let fourth = todo("fourth");
+        let after = vec![edited.clone(), third.clone(), fourth.clone()];
+
+        let changes = diff(&before, &after);
+        assert_eq!(
+            changes,
+            vec![
+                Change::Remove { id: second.id },
+                Change::Update { todo: edited },
+                Change::Insert { todo: fourth },
+            ]
+        );
+
+        // Deleting swaps the last TODO into the gap
+        let after = vec![third.clone(), second.clone()];
+        let changes = diff(&before, &after);
+        assert_eq!(changes.len(), 2);
+        let mut result = before.clone();
+        for change in changes {
+            apply_change(&mut result, change);
+        }
+        assert_eq!(result, after);
+
+        assert!(diff(&before, &before).is_empty());
+    }
+
+    #[test]
+    fn test_memory_storage() {
+        check_backend(&mut MemoryStorage::default());
+    }
+
+    #[test]
+    fn test_json_storage() {
+        let dir = temp_path("json_storage");
+        check_backend(&mut JsonFileStorage::new(dir.join("todos.json")));
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_pretty_json_storage() {
+        let dir = temp_path("json_pretty");
+        let path = dir.join("todos.json");
+        let mut storage = JsonFileStorage::new(path.clone()).pretty(true);
+        check_backend(&mut storage);
+
+        let mut first = todo("first");
+        first.id = Uuid::nil();
+        storage.save(std::slice::from_ref(&first)).unwrap();
+        let content = std::fs::read(&path).unwrap();
+        assert_eq!(
+            String::from_utf8_lossy(&content),
+            "{\n  \"version\": 2,\n  \"todos\": [\n    {\n      \
+             \"id\": \"00000000-0000-0000-0000-000000000000\",\n      \
+             \"text\": \"first\",\n      \"completed\": false\n    }\n  ]\n}\n"
+        );
+        let todos = storage.load().unwrap();
+        storage.save(&todos).unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), content);
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_json_storage_skips_unchanged_writes() {
+        let dir = temp_path("json_unchanged");
+        let path = dir.join("todos.json");
+        let modified = || std::fs::metadata(&path).unwrap().modified().unwrap();
+        let mut todos = vec![todo("first")];
+
+        let mut storage = JsonFileStorage::new(path.clone());
+        storage.save(&todos).unwrap();
+        let saved = modified();
+        std::thread::sleep(std::time::Duration::from_millis(10));
+        storage.save(&todos).unwrap();
+        assert_eq!(modified(), saved);
+
+        // Changed by another program
+        std::fs::write(&path, b"[]").unwrap();
+        storage.save(&todos).unwrap();
+        assert_eq!(storage.load().unwrap(), todos);
+
+        let loaded = modified();
+        std::thread::sleep(std::time::Duration::from_millis(10));
+        todos[0].completed = true;
+        storage.save(&todos).unwrap();
+        assert_ne!(modified(), loaded);
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_log_storage() {
+        let dir = temp_path("log_storage");
+        check_backend(&mut EventLogStorage::new(dir.join("todos"), 2));
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_json_storage_migrates_old_files() {
+        let dir = temp_path("json_migration");
+        let path = dir.join("todos.json");
+        let old = r#"[{"text":"first","completed":true}]"#;
+        std::fs::write(&path, old).unwrap();
+
+        let mut storage = JsonFileStorage::new(path.clone());
+        let todos = storage.load().unwrap();
+        assert_eq!(todos.len(), 1);
+        assert_eq!(todos[0].text, "first");
+        assert!(todos[0].completed);
+        assert_eq!(storage.load().unwrap(), todos);
+
+        storage.save(&todos).unwrap();
+        let saved: serde_json::Value =
+            serde_json::from_slice(&std::fs::read(&path).unwrap()).unwrap();
+        assert_eq!(saved["version"], schema::CURRENT_VERSION);
+        assert_eq!(
+            std::fs::read_to_string(storage.backup_path(0)).unwrap(),
+            old
+        );
+
+        std::fs::write(&path, r#"{"version":999,"todos":[]}"#).unwrap();
+        assert!(storage.load().is_err());
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_encrypted_json_storage() {
+        let dir = temp_path("json_encrypted");
+        let path = dir.join("todos.json");
+        let todos = vec![todo("first"), todo("second")];
+
+        let mut inputs = vec!["secret", "secret"].into_iter();
+        let mut storage = JsonFileStorage::new(path.clone());
+        storage
+            .unlock(|| Ok(inputs.next().unwrap().to_string()))
+            .unwrap();
+        storage.save(&todos).unwrap();
+        let content = std::fs::read(&path).unwrap();
+        assert!(crypto::is_encrypted(&content));
+        assert!(!String::from_utf8_lossy(&content).contains("first"));
+        assert!(storage.is_encrypted());
+        assert!(!JsonFileStorage::new(dir.join("plain.json")).is_encrypted());
+
+        // Without passphrase, with a wrong one and after three wrong ones
+        assert!(JsonFileStorage::new(path.clone()).load().is_err());
+        let mut inputs = vec!["wrong", "secret"].into_iter();
+        let mut storage = JsonFileStorage::new(path.clone());
+        storage
+            .unlock(|| Ok(inputs.next().unwrap().to_string()))
+            .unwrap();
+        assert_eq!(storage.load().unwrap(), todos);
+        let mut storage = JsonFileStorage::new(path.clone());
+        assert!(storage.unlock(|| Ok("wrong".to_string())).is_err());
+        assert_eq!(std::fs::read(&path).unwrap(), content);
+
+        let mut inputs = vec!["secret"].into_iter();
+        let mut storage = JsonFileStorage::new(path.clone());
+        storage
+            .unlock(|| Ok(inputs.next().unwrap().to_string()))
+            .unwrap();
+        storage.change_passphrase("changed").unwrap();
+        let mut storage = JsonFileStorage::new(path.clone());
+        assert!(storage.unlock(|| Ok("secret".to_string())).is_err());
+        let mut storage = JsonFileStorage::new(path.clone());
+        storage.unlock(|| Ok("changed".to_string())).unwrap();
+        assert_eq!(storage.load().unwrap(), todos);
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_directory_storage() {
+        let dir = temp_path("directory_storage");
+        check_backend(&mut DirectoryStorage::new(dir.join("todos")));
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,156 @@
+use crate::{errors::ApplicationError, todo::Todo};
+use serde::Serialize;
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+/// Version of the data file written by this build.
+pub const CURRENT_VERSION: u64 = 2;
+
+type Migration = fn(Value) -> Result<Value, ApplicationError>;
+
+/// `MIGRATIONS[n]` upgrades a version `n` file to version `n + 1`.
+const MIGRATIONS: [Migration; CURRENT_VERSION as usize] = [v0_to_v1, v1_to_v2];
+
+/// A data file of the current version. Serializes its fields and those of the TODOs
+/// in declaration order, so the layout of written files is stable.
+#[derive(Serialize)]
+pub struct Envelope<'a> {
+    pub version: u64,
+    pub todos: &'a [Todo],
+}
+
+/// Wraps `todos` in the envelope of the current version.
+pub fn envelope(todos: &[Todo]) -> Envelope<'_> {
+    Envelope {
+        version: CURRENT_VERSION,
+        todos,
+    }
+}
+
+/// The version of a data file. Version 0 is the bare array of TODOs written before
+/// the file had a version.
+pub fn version_of(data: &Value) -> Result<u64, ApplicationError> {
+    match data {
+        Value::Array(_) => Ok(0),
+        Value::Object(object) => object
+            .get("version")
+            .and_then(Value::as_u64)
+            .ok_or_else(|| ApplicationError("The data file has no valid version".to_string())),
+        _ => Err(ApplicationError(
+            "The data file has an unknown format".to_string(),
+        )),
+    }
+}
+
+/// Upgrades `data` to the current version and returns the TODOs of the envelope.
+pub fn migrate(mut data: Value) -> Result<Value, ApplicationError> {
+    let version = version_of(&data)?;
+    if version > CURRENT_VERSION {
+        return Err(ApplicationError(format!(
+            "The data file has version {}, but this build only supports up to version {}. Please update.",
+            version, CURRENT_VERSION
+        )));
+    }
+
+    for migration in &MIGRATIONS[version as usize..] {
+        data = migration(data)?;
+    }
+
+    match data {
+        Value::Object(mut object) => Ok(object.remove("todos").unwrap_or(Value::Array(Vec::new()))),
+        _ => Err(ApplicationError(
+            "The data file has an unknown format".to_string(),
+        )),
+    }
+}
+
+/// Wraps the bare array in the envelope. TODOs from before ids get one derived from
+/// their position and text, so migrating the same file twice yields the same ids.
+fn v0_to_v1(data: Value) -> Result<Value, ApplicationError> {
+    let Value::Array(mut todos) = data else {
+        return Err(ApplicationError("Expected a list of TODOs".to_string()));
+    };
+
+    for (i, todo) in todos.iter_mut().enumerate() {
+        let Value::Object(todo) = todo else {
+            return Err(ApplicationError(format!(
+                "TODO #{} is not an object",
+                i + 1
+            )));
+        };
+        if !todo.contains_key("id") {
+            let text = todo.get("text").and_then(Value::as_str).unwrap_or_default();
+            let name = format!("{}:{}", i, text);
+            let id = Uuid::new_v5(&Uuid::NAMESPACE_OID, name.as_bytes());
+            todo.insert("id".to_string(), Value::String(id.to_string()));
+        }
+    }
+    Ok(json!({ "version": 1, "todos": todos }))
+}
+
+/// Version 2 added priority, the creation and completion dates, the list, the parent
+/// and the metadata of TODOs. They are optional, so only the version changes, which
+/// keeps older builds from dropping them when saving.
+fn v1_to_v2(data: Value) -> Result<Value, ApplicationError> {
+    let Value::Object(mut object) = data else {
+        return Err(ApplicationError(
+            "The data file has an unknown format".to_string(),
+        ));
+    };
+    object.insert("version".to_string(), json!(2));
+    Ok(Value::Object(object))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_migrate_bare_array() {
+        let data = json!([
+            { "text": "first", "completed": false },
+            { "id": "6f1f0f2e-7c3e-4c1a-9e0a-6a1f0d3e9b21", "text": "second", "completed": true },
+        ]);
+        assert_eq!(version_of(&data).unwrap(), 0);
+
+        let todos = migrate(data.clone()).unwrap();
+        assert_eq!(todos.as_array().unwrap().len(), 2);
+        assert!(todos[0]["id"].is_string());
+        assert_eq!(todos[1]["id"], "6f1f0f2e-7c3e-4c1a-9e0a-6a1f0d3e9b21");
+        assert_eq!(migrate(data).unwrap(), todos);
+    }
+
+    #[test]
+    fn test_migrate_v1() {
+        let data = json!({
+            "version": 1,
+            "todos": [{ "id": "6f1f0f2e-7c3e-4c1a-9e0a-6a1f0d3e9b21", "text": "first", "completed": false }],
+        });
+        let todos = migrate(data).unwrap();
+        let todos: Vec<Todo> = serde_json::from_value(todos).unwrap();
+        assert_eq!(todos[0].text, "first");
+        assert_eq!(todos[0].priority, None);
+        assert!(todos[0].metadata.is_empty());
+
+        assert_eq!(
+            v1_to_v2(json!({ "version": 1, "todos": [] })).unwrap()["version"],
+            2
+        );
+        assert!(v1_to_v2(json!([])).is_err());
+    }
+
+    #[test]
+    fn test_migrate_current_and_newer() {
+        let todos = vec![Todo::new("first".to_string())];
+        let data = serde_json::to_value(envelope(&todos)).unwrap();
+        assert_eq!(
+            migrate(data).unwrap(),
+            serde_json::to_value(&todos).unwrap()
+        );
+
+        let newer = json!({ "version": CURRENT_VERSION + 1, "todos": todos });
+        assert!(migrate(newer).is_err());
+        assert!(migrate(json!({ "todos": [] })).is_err());
+        assert!(migrate(json!("todos")).is_err());
+    }
+}
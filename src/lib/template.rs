@@ -0,0 +1,358 @@
+//! A small template language to format TODOs, e.g. `[{check}] {text:<40} {?due}due {due}{/}`.
+//!
+//! - `{field}` is replaced by a field of the TODO: `id`, `text`, `completed`, `check`
+//!   (`x` if completed), `priority`, `created`, `completed_at`, `due`, `list`,
+//!   `parent`, `projects` and `contexts`. Any other name is a metadata key.
+//! - `{field:<20}` and `{field:>20}` pad the value to 20 characters, aligned left or
+//!   right.
+//! - `{?field}...{/}` is only shown if the field is set, neither blank nor `false`,
+//!   so `{?check}` is shown for completed TODOs only. `{!field}` is shown only if it
+//!   isn't set. `{:}` starts the part shown otherwise.
+//! - `{{` and `}}` are literal braces.
+
+use crate::{errors::ApplicationError, todo::Todo};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+
+/// The format the TODOs always had.
+pub const DEFAULT: &str = "completed: {completed} | text: {text}";
+
+/// The `templates` section of the config file.
+#[derive(Deserialize, Debug, Default, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct TemplateConfig {
+    /// Named formats, to choose for the views or an export
+    pub formats: BTreeMap<String, String>,
+    /// Name of the format of listed TODOs
+    pub list: Option<String>,
+    /// Name of the format of TODOs to select from by number
+    pub select: Option<String>,
+}
+
+/// The parsed formats of a `TemplateConfig`.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Templates {
+    pub list: Template,
+    pub select: Template,
+    pub formats: BTreeMap<String, Template>,
+}
+
+impl TemplateConfig {
+    pub fn templates(&self) -> Result<Templates, ApplicationError> {
+        let formats = self
+            .formats
+            .iter()
+            .map(|(name, source)| Ok((name.clone(), Template::parse(source)?)))
+            .collect::<Result<BTreeMap<_, _>, ApplicationError>>()?;
+        let view = |name: &Option<String>| match name {
+            Some(name) => formats
+                .get(name)
+                .cloned()
+                .ok_or_else(|| ApplicationError(format!("There is no format named {}", name))),
+            None => Ok(Template::default()),
+        };
+        Ok(Templates {
+            list: view(&self.list)?,
+            select: view(&self.select)?,
+            formats: formats.clone(),
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Align {
+    Left,
+    Right,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Part {
+    Text(String),
+    Field {
+        name: String,
+        align: Align,
+        width: usize,
+    },
+    Condition {
+        name: String,
+        negated: bool,
+        then: Vec<Part>,
+        otherwise: Vec<Part>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Template {
+    parts: Vec<Part>,
+}
+
+impl Default for Template {
+    fn default() -> Self {
+        Template::parse(DEFAULT).unwrap()
+    }
+}
+
+/// A condition being parsed.
+struct Open {
+    name: String,
+    negated: bool,
+    then: Vec<Part>,
+    /// Set once `{:}` was read
+    otherwise: Option<Vec<Part>>,
+}
+
+impl Template {
+    pub fn parse(source: &str) -> Result<Template, ApplicationError> {
+        let invalid =
+            |reason: &str| ApplicationError(format!("Invalid template \"{}\": {}", source, reason));
+        let mut parts = Vec::new();
+        let mut open: Vec<Open> = Vec::new();
+        let mut text = String::new();
+        let mut chars = source.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    text.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    text.push('}');
+                }
+                '}' => return Err(invalid("} without {")),
+                '{' => {
+                    let mut tag = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(c) => tag.push(c),
+                            None => return Err(invalid("{ without }")),
+                        }
+                    }
+
+                    if !text.is_empty() {
+                        push_part(&mut open, &mut parts, Part::Text(std::mem::take(&mut text)));
+                    }
+
+                    let tag = tag.trim();
+                    if tag == "/" {
+                        let condition =
+                            open.pop().ok_or_else(|| invalid("{/} without condition"))?;
+                        let part = Part::Condition {
+                            name: condition.name,
+                            negated: condition.negated,
+                            then: condition.then,
+                            otherwise: condition.otherwise.unwrap_or_default(),
+                        };
+                        push_part(&mut open, &mut parts, part);
+                    } else if tag == ":" {
+                        match open.last_mut() {
+                            Some(condition) if condition.otherwise.is_none() => {
+                                condition.otherwise = Some(Vec::new())
+                            }
+                            _ => return Err(invalid("{:} without condition")),
+                        }
+                    } else if let Some(name) = tag.strip_prefix('?').or(tag.strip_prefix('!')) {
+                        if name.trim().is_empty() {
+                            return Err(invalid("condition without field"));
+                        }
+                        open.push(Open {
+                            name: name.trim().to_string(),
+                            negated: tag.starts_with('!'),
+                            then: Vec::new(),
+                            otherwise: None,
+                        });
+                    } else {
+                        let part = parse_field(tag).ok_or_else(|| {
+                            invalid(&format!("{{{}}} is no valid placeholder", tag))
+                        })?;
+                        push_part(&mut open, &mut parts, part);
+                    }
+                }
+                c => text.push(c),
+            }
+        }
+
+        if !open.is_empty() {
+            return Err(invalid("condition without {/}"));
+        }
+        if !text.is_empty() {
+            parts.push(Part::Text(text));
+        }
+        Ok(Template { parts })
+    }
+
+    pub fn render(&self, todo: &Todo) -> String {
+        let mut out = String::new();
+        render_parts(&self.parts, todo, &mut out);
+        out
+    }
+}
+
+/// Adds a part to the innermost open condition, or the template if there is none.
+fn push_part(open: &mut [Open], parts: &mut Vec<Part>, part: Part) {
+    match open.last_mut() {
+        Some(Open {
+            otherwise: Some(otherwise),
+            ..
+        }) => otherwise.push(part),
+        Some(condition) => condition.then.push(part),
+        None => parts.push(part),
+    }
+}
+
+/// A `name` or `name:<width` placeholder.
+fn parse_field(tag: &str) -> Option<Part> {
+    let (name, spec) = tag.split_once(':').unwrap_or((tag, ""));
+    let name = name.trim();
+    if name.is_empty() {
+        return None;
+    }
+    let (align, width) = match spec.strip_prefix('>') {
+        Some(width) => (Align::Right, width),
+        None => (Align::Left, spec.strip_prefix('<').unwrap_or(spec)),
+    };
+    let width = match width {
+        "" => 0,
+        width => width.parse().ok()?,
+    };
+    Some(Part::Field {
+        name: name.to_string(),
+        align,
+        width,
+    })
+}
+
+fn render_parts(parts: &[Part], todo: &Todo, out: &mut String) {
+    for part in parts {
+        match part {
+            Part::Text(text) => out.push_str(text),
+            Part::Field { name, align, width } => {
+                let value = field(todo, name);
+                let padding = " ".repeat(width.saturating_sub(value.chars().count()));
+                match align {
+                    Align::Left => {
+                        out.push_str(&value);
+                        out.push_str(&padding);
+                    }
+                    Align::Right => {
+                        out.push_str(&padding);
+                        out.push_str(&value);
+                    }
+                }
+            }
+            Part::Condition {
+                name,
+                negated,
+                then,
+                otherwise,
+            } => {
+                let value = field(todo, name);
+                // Blank like `check` of open TODOs
+                let set = !value.trim().is_empty() && value != "false";
+                if set != *negated {
+                    render_parts(then, todo, out);
+                } else {
+                    render_parts(otherwise, todo, out);
+                }
+            }
+        }
+    }
+}
+
+/// The value of a field, empty if it isn't set.
+fn field(todo: &Todo, name: &str) -> String {
+    let date = |date: Option<chrono::NaiveDate>| {
+        date.map(|date| date.format("%Y-%m-%d").to_string())
+            .unwrap_or_default()
+    };
+    let tags = |tags: Vec<&str>, prefix: char| {
+        tags.iter()
+            .map(|tag| format!("{}{}", prefix, tag))
+            .collect::<Vec<_>>()
+            .join(" ")
+    };
+    match name {
+        "id" => todo.id.to_string(),
+        "text" => todo.text.clone(),
+        "completed" => todo.completed.to_string(),
+        "check" => if todo.completed { "x" } else { " " }.to_string(),
+        "priority" => todo.priority.map(String::from).unwrap_or_default(),
+        "created" => date(todo.created),
+        "completed_at" => date(todo.completed_at),
+        "due" => date(todo.due()),
+        "list" => todo.list.clone().unwrap_or_default(),
+        "parent" => todo.parent.map(|id| id.to_string()).unwrap_or_default(),
+        "projects" => tags(todo.projects(), '+'),
+        "contexts" => tags(todo.contexts(), '@'),
+        key => todo.metadata.get(key).cloned().unwrap_or_default(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        let mut todo = Todo::new("Call mom +family @phone".to_string());
+        todo.priority = Some('A');
+        todo.metadata
+            .insert("estimate".to_string(), "1h".to_string());
+        let render = |source: &str, todo: &Todo| Template::parse(source).unwrap().render(todo);
+
+        assert_eq!(
+            Template::default().render(&todo),
+            "completed: false | text: Call mom +family @phone"
+        );
+        assert_eq!(
+            render("[{check}] {priority:>3}|{projects:<9}|{estimate}", &todo),
+            "[ ]   A|+family  |1h"
+        );
+        assert_eq!(render("{{{text:3}}}", &todo), "{Call mom +family @phone}");
+
+        let source = "{?completed}done{:}open{?priority} ({priority}){/}{/}{!list}, no list{/}";
+        assert_eq!(render(source, &todo), "open (A), no list");
+        todo.completed = true;
+        todo.list = Some("Home".to_string());
+        assert_eq!(render(source, &todo), "done");
+
+        let source = "{?check}[{check}] {/}{!check}open {/}{?blank}blank{/}";
+        todo.metadata.insert("blank".to_string(), "  ".to_string());
+        assert_eq!(render(source, &todo), "[x] ");
+        todo.completed = false;
+        assert_eq!(render(source, &todo), "open ");
+    }
+
+    #[test]
+    fn test_invalid() {
+        for source in [
+            "{text",
+            "text}",
+            "{?completed}",
+            "{/}",
+            "{:}",
+            "{?completed}{:}{:}{/}",
+            "{text:x}",
+            "{}",
+            "{?}{/}",
+        ] {
+            assert!(Template::parse(source).is_err(), "{}", source);
+        }
+
+        let config = TemplateConfig {
+            formats: BTreeMap::from([("short".to_string(), "[{check}] {text}".to_string())]),
+            list: Some("short".to_string()),
+            select: Some("long".to_string()),
+        };
+        assert!(config.templates().is_err());
+        let config = TemplateConfig {
+            select: None,
+            ..config
+        };
+        let templates = config.templates().unwrap();
+        assert_eq!(templates.list, templates.formats["short"]);
+        assert_eq!(templates.select, Template::default());
+    }
+}
@@ -1,17 +1,88 @@
+use chrono::NaiveDate;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use uuid::Uuid;
 
+/// Metadata key of the due date, as todo.txt has it
+pub const DUE_KEY: &str = "due";
+const DATE_FORMAT: &str = "%Y-%m-%d";
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
 pub struct Todo {
+    pub id: Uuid,
     pub text: String,
     pub completed: bool,
+    /// From `A`, the highest, to `Z`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub priority: Option<char>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created: Option<NaiveDate>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub completed_at: Option<NaiveDate>,
+    /// Name of the list the TODO is on, `None` for the default list
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub list: Option<String>,
+    /// The TODO this one is a subtask of
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent: Option<Uuid>,
+    /// `key:value` pairs this program doesn't know, kept as they were imported
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub metadata: BTreeMap<String, String>,
 }
 
 impl Todo {
     pub fn new(text: String) -> Self {
         Self {
+            id: Uuid::new_v4(),
             text,
-            completed: false,
+            ..Default::default()
         }
     }
-}
\ No newline at end of file
+
+    /// The due date, `None` if there is none or it is no valid date.
+    pub fn due(&self) -> Option<NaiveDate> {
+        let due = self.metadata.get(DUE_KEY)?;
+        NaiveDate::parse_from_str(due, DATE_FORMAT).ok()
+    }
+
+    pub fn set_due(&mut self, due: Option<NaiveDate>) {
+        match due {
+            Some(due) => {
+                let due = due.format(DATE_FORMAT).to_string();
+                self.metadata.insert(DUE_KEY.to_string(), due);
+            }
+            None => {
+                self.metadata.remove(DUE_KEY);
+            }
+        }
+    }
+
+    /// Completing a TODO sets its completion date to `today`, unless it was completed
+    /// already, opening it again removes the date.
+    pub fn set_completed(&mut self, completed: bool, today: NaiveDate) {
+        if !completed {
+            self.completed_at = None;
+        } else if !self.completed {
+            self.completed_at = Some(today);
+        }
+        self.completed = completed;
+    }
+
+    /// The `+project` tags in the text.
+    pub fn projects(&self) -> Vec<&str> {
+        self.tags('+')
+    }
+
+    /// The `@context` tags in the text.
+    pub fn contexts(&self) -> Vec<&str> {
+        self.tags('@')
+    }
+
+    fn tags(&self, prefix: char) -> Vec<&str> {
+        self.text
+            .split_whitespace()
+            .filter_map(|word| word.strip_prefix(prefix))
+            .filter(|tag| !tag.is_empty())
+            .collect()
+    }
+}
@@ -1,64 +1,380 @@
 use std::{
     cell::RefCell,
-    fs::File,
-    io::{stdout, BufReader, Error, Write},
+    io::{stdout, ErrorKind, Write},
+    path::{Path, PathBuf},
     process::exit,
-    sync::Arc,
+    rc::Rc,
 };
 use todolib::{
     action::{self, Action},
-    errors::SelectionError,
+    cli::{self, Command, Outcome},
+    config::{Config, CONFIG_FILE},
+    crdt::Replica,
+    doctor,
+    errors::{ApplicationError, SelectionError},
+    formats::csv::CsvConfig,
     get_input,
+    git::{self, GitRepo},
+    lock::FileLock,
+    merge,
+    output::OutputFormat,
+    snapshot::SnapshotStore,
+    storage::{self, Storage},
+    template::Templates,
     todo::Todo,
     Todos,
 };
 
-fn store(todos: Todos) -> Result<(), Error> {
-    let json = serde_json::to_string(&*todos.clone().borrow())?;
-    std::fs::write("todos.json", json)?;
-    return Ok(());
+/// What an interactive session works on.
+struct Session {
+    storage: Box<dyn Storage>,
+    todos: Todos,
+    /// The TODOs as last loaded from or written to the storage. Anything else found
+    /// in the storage on store was written by another program meanwhile.
+    base: Vec<Todo>,
+    /// Whether actions changed the TODOs since they were last written
+    dirty: bool,
+    read_only: bool,
+    git: Option<GitRepo>,
+    /// The TODOs as of the last git commit, to describe the changes in the next one
+    committed: Vec<Todo>,
+    /// The CRDT replica and its file, if enabled
+    replica: Option<(Replica, PathBuf)>,
+    snapshots: SnapshotStore,
+    /// Columns of CSV exports
+    csv: CsvConfig,
+    templates: Templates,
 }
 
-fn load() -> std::io::Result<Todos> {
-    let todos: Todos = Arc::new(RefCell::new(Vec::new()));
-    let file = File::open("todos.json")?;
-    let buf_reader = BufReader::new(file);
-    let mut todos_temp: Vec<Todo> = serde_json::from_reader(buf_reader)?;
-    todos.borrow_mut().append(&mut todos_temp);
-    return Ok(todos);
+impl Session {
+    fn store(&mut self) -> Result<(), ApplicationError> {
+        let mut ours = self.todos.borrow().clone();
+
+        let theirs = self.storage.load()?;
+        if theirs == self.base && !self.dirty {
+            return Ok(()); // Nothing to write
+        }
+        if theirs != self.base {
+            println!("The stored TODOs were changed by another program, merging...");
+            let mut result = merge::three_way_merge(&self.base, &ours, &theirs);
+            if !result.conflicts.is_empty() {
+                println!(
+                    "{} TODO(s) were changed on both sides.",
+                    result.conflicts.len()
+                );
+                merge::resolve_conflicts(&mut result, &self.templates.list)?;
+            }
+            ours = result.todos;
+        }
+
+        self.storage.save(&ours)?;
+        if let Some((replica, path)) = &mut self.replica {
+            replica.update(&ours);
+            replica.save(path)?;
+        }
+        self.dirty = false;
+        self.base = ours.clone();
+        *self.todos.borrow_mut() = ours;
+        Ok(())
+    }
+
+    /// Like `store`, for commands whose output may be read by programs: the notice goes
+    /// to stderr, and conflicts with changes another program stored meanwhile are an
+    /// error instead of asking which version to keep.
+    fn store_unattended(&mut self) -> Result<(), cli::Error> {
+        let theirs = self.storage.load()?;
+        if theirs != self.base {
+            eprintln!("The stored TODOs were changed by another program, merging...");
+            let result = merge::three_way_merge(&self.base, &self.todos.borrow(), &theirs);
+            if !result.conflicts.is_empty() {
+                return Err(cli::Error::Conflict(format!(
+                    "{} TODO(s) were changed by another program as well, nothing was stored",
+                    result.conflicts.len()
+                )));
+            }
+            *self.todos.borrow_mut() = result.todos;
+            self.base = theirs;
+            self.dirty = true;
+        }
+        Ok(self.store()?)
+    }
+
+    /// Hands the changes of the last action to the storage. Once it persisted them
+    /// they are part of `base`, otherwise the session is dirty until the next store.
+    fn record_changes(&mut self, before: &[Todo]) {
+        let changes = storage::diff(before, &self.todos.borrow());
+        if changes.is_empty() {
+            return;
+        }
+        // Stamps the changes with the time they were made, not when they are stored
+        if let Some((replica, _)) = &mut self.replica {
+            replica.update(&self.todos.borrow());
+        }
+        match self.storage.record(&changes) {
+            Ok(true) => self.base = self.todos.borrow().clone(),
+            Ok(false) => self.dirty = true,
+            Err(err) => {
+                self.dirty = true;
+                eprintln!("Error storing changes: {}", err);
+                std::thread::sleep(core::time::Duration::from_secs(1));
+            }
+        }
+    }
+
+    /// Commits the stored TODOs to git, if enabled.
+    fn commit(&mut self) -> Result<(), ApplicationError> {
+        let Some(git) = &self.git else {
+            return Ok(());
+        };
+        let todos = self.todos.borrow().clone();
+        // Data can change without changing TODOs, e.g. when migrated or re-encrypted
+        let message = git::describe_changes(&self.committed, &todos)
+            .unwrap_or_else(|| "Update TODO data".to_string());
+        git.commit(&message)?;
+        self.committed = todos;
+        Ok(())
+    }
+
+    fn sync(&mut self) -> Result<(), ApplicationError> {
+        if self.git.is_none() {
+            return Err(ApplicationError(
+                "Git is not enabled in the config".to_string(),
+            ));
+        }
+        self.store()?;
+        self.commit()?;
+
+        let todos = self.todos.borrow().clone();
+        if let Some(git) = &self.git {
+            let todos = git.sync(self.storage.as_mut(), &todos, &self.templates.list)?;
+            self.base = todos.clone();
+            self.committed = todos.clone();
+            *self.todos.borrow_mut() = todos;
+        }
+
+        println!("Successfully synced with the git remote.");
+        std::thread::sleep(core::time::Duration::from_secs(1));
+        Ok(())
+    }
+
+    fn sync_file(&mut self) -> Result<(), ApplicationError> {
+        let Some((replica, _)) = &mut self.replica else {
+            return Err(ApplicationError(
+                "The replica is not enabled in the config".to_string(),
+            ));
+        };
+        let before = self.todos.borrow().clone();
+        action::sync_file(replica, self.todos.clone())?;
+        self.record_changes(&before);
+        self.store()
+    }
+}
+
+/// Locks the data file. If another instance holds the lock, the user can continue
+/// in read-only mode (`None`) or exit.
+fn acquire_lock(data_path: &Path) -> Option<FileLock> {
+    match FileLock::acquire(data_path) {
+        Ok(lock) => Some(lock),
+        Err(err) if err.kind() == ErrorKind::WouldBlock => {
+            let name = data_path.display();
+            match FileLock::holder(data_path) {
+                Some(pid) => println!("{} is in use by another instance (PID {}).", name, pid),
+                None => println!("{} is in use by another instance.", name),
+            }
+            print!("Open it read-only? Changes will not be stored. [y/N]: ");
+            let _ = stdout().flush();
+
+            match get_input() {
+                Ok(input) if input.eq_ignore_ascii_case("y") => None,
+                _ => {
+                    println!("Exiting, Bye!");
+                    exit(0);
+                }
+            }
+        }
+        Err(err) => {
+            println!("Error locking {}: {}, exit.", data_path.display(), err);
+            exit(-1);
+        }
+    }
+}
+
+/// Checks the stored data for problems and repairs them, unless `dry_run` is set.
+fn run_doctor(config: &Config, dry_run: bool) -> Result<Outcome, ApplicationError> {
+    let _lock =
+        match config.storage.path() {
+            Some(path) if !dry_run => Some(FileLock::acquire(path).map_err(|err| {
+                ApplicationError(format!("Can't lock {}: {}", path.display(), err))
+            })?),
+            _ => None,
+        };
+    let mut storage = config.storage.open()?;
+    let report = doctor::check_storage(storage.as_mut())?;
+
+    let repairable = report.todos.is_some();
+    let mut backup = None;
+    if let Some(todos) = report
+        .todos
+        .filter(|_| !report.findings.is_empty() && !dry_run)
+    {
+        if let Some(path) = config.storage.path().filter(|path| path.is_file()) {
+            let mut copy = path.as_os_str().to_owned();
+            copy.push(".doctor.bak");
+            std::fs::copy(path, &copy)?;
+            backup = Some(PathBuf::from(copy));
+        }
+        storage.save(&todos)?;
+    }
+    Ok(Outcome::Doctor {
+        findings: report.findings,
+        repairable,
+        dry_run,
+        backup,
+        content_checked: report.content_checked,
+    })
+}
+
+/// Opens and loads the storage and what else the config enables.
+fn open_session(
+    config: &Config,
+    templates: Templates,
+    read_only: bool,
+) -> Result<Session, ApplicationError> {
+    let context = |what: &'static str| {
+        move |err: ApplicationError| ApplicationError(format!("Error {}: {}", what, err))
+    };
+    let mut storage = config.storage.open().map_err(context("opening storage"))?;
+    // Going on with an empty list would overwrite the data when storing
+    let base = storage.load().map_err(context("loading data"))?;
+    let git = match &config.git {
+        Some(git_config) if !read_only => Some(
+            GitRepo::open(git_config, &config.storage)
+                .map_err(context("opening git repository"))?,
+        ),
+        _ => None,
+    };
+    // Read-only instances must not write the replica the other instance uses
+    let replica = match &config.replica {
+        Some(replica_config) if !read_only => {
+            let mut replica =
+                Replica::load(&replica_config.path).map_err(context("loading replica"))?;
+            // Picks up changes made while the replica wasn't tracking them
+            replica.update(&base);
+            Some((replica, replica_config.path.clone()))
+        }
+        _ => None,
+    };
+    Ok(Session {
+        storage,
+        todos: Rc::new(RefCell::new(base.clone())),
+        committed: base.clone(),
+        base,
+        dirty: false,
+        read_only,
+        git,
+        replica,
+        snapshots: SnapshotStore::new(config.snapshot_dir()),
+        csv: config.csv.clone(),
+        templates,
+    })
+}
+
+/// Runs a subcommand instead of the menu and stores its changes.
+fn run_command(
+    config: &Config,
+    templates: Templates,
+    command: &Command,
+) -> Result<Outcome, cli::Error> {
+    let write = command.needs_write_access();
+    // Never waits for an answer like the menu does, another instance is an error
+    let _lock = match config.storage.path() {
+        Some(path) if write => Some(FileLock::acquire(path).map_err(|err| {
+            match (err.kind(), FileLock::holder(path)) {
+                (ErrorKind::WouldBlock, Some(pid)) => ApplicationError(format!(
+                    "{} is in use by another instance (PID {})",
+                    path.display(),
+                    pid
+                )),
+                _ => ApplicationError(format!("Can't lock {}: {}", path.display(), err)),
+            }
+        })?),
+        _ => None,
+    };
+    let mut session = open_session(config, templates, !write)?;
+
+    let before = session.todos.borrow().clone();
+    let outcome = command.run(&mut session.todos.borrow_mut())?;
+    if write {
+        session.record_changes(&before);
+        session.store_unattended()?;
+        session.commit()?;
+    }
+    Ok(outcome)
 }
 
-fn print_main() {
+fn print_main(read_only: bool) {
     println!("\n########################################");
     println!("############# TODO Manager #############");
     println!("########################################");
+    if read_only {
+        println!("\n[read-only: the data is in use by another instance]");
+    }
     println!("\nAvailable Actions:");
     println!("1. Create TODO");
     println!("2. Edit TODO");
     println!("3. Delete TODO");
     println!("4. List TODOs");
     println!("5. Complete TODO");
+    println!("7. Change passphrase");
+    println!("8. Sync with git remote");
+    println!("9. Sync with replica file");
+    println!("10. Create snapshot");
+    println!("11. Browse snapshots");
+    println!("12. Import TODOs");
+    println!("13. Export TODOs");
+    println!("14. Scan source for TODO comments");
     println!("6. Exit");
-    println!("");
+    println!();
 
     print!("Enter your action: ");
     let _ = stdout().flush(); // This is necessary, otherwise the text appears after the next println
 }
 
-fn execute_action(exit_app: &mut bool, todos: &Arc<RefCell<Vec<Todo>>>, action: Action) {
+fn execute_action(exit_app: &mut bool, session: &mut Session, action: Action) {
+    let todos = session.todos.clone();
     if let Err(err) = match action {
-        Action::Create => action::create_todo(todos.clone()),
-        Action::Edit => action::edit_todo(todos.clone()),
-        Action::Delete => action::delete_todo(todos.clone()),
-        Action::List => action::list_todos(todos.clone()),
-        Action::Complete => action::complete_todo(todos.clone()),
+        _ if session.read_only && action.needs_write_access() => Err(ApplicationError(
+            "The TODO list was opened read-only and can't be changed.".to_string(),
+        )),
+        Action::Create => action::create_todo(todos),
+        Action::Edit => action::edit_todo(todos, &session.templates.select),
+        Action::Delete => action::delete_todo(todos, &session.templates.select),
+        Action::List => action::list_todos(todos, &session.templates.list),
+        Action::Complete => action::complete_todo(todos, &session.templates.select),
+        Action::ChangePassphrase => action::change_passphrase(session.storage.as_mut()),
+        Action::Sync => session.sync(),
+        Action::SyncFile => session.sync_file(),
+        // Snapshots are plain JSON files, they would leak encrypted TODOs
+        Action::Snapshot if session.storage.is_encrypted() => Err(ApplicationError(
+            "Snapshots aren't encrypted, they can't be taken of encrypted data.".to_string(),
+        )),
+        Action::Snapshot => action::create_snapshot(&session.snapshots, todos),
+        Action::BrowseSnapshots => action::browse_snapshots(
+            &session.snapshots,
+            todos,
+            &session.templates,
+            session.read_only,
+        ),
+        Action::Import => action::import_todos(todos, &session.templates.list),
+        Action::Export => action::export_todos(todos, &session.csv, &session.templates),
+        Action::Scan => action::scan_source(todos),
         Action::Exit => {
             *exit_app = true;
             Ok(())
         }
         Action::Invalid => Err(SelectionError("Invalid Selection".to_string()).into()),
     } {
-        println!("{}", err.to_string());
+        println!("{}", err);
         std::thread::sleep(core::time::Duration::from_secs(1));
     }
 }
@@ -72,7 +388,7 @@ fn clean_console() {
             .unwrap_or_else(|status| {
                 println!(
                     "An error occurred while clearing the screen: {}, exit.",
-                    status.to_string()
+                    status
                 );
                 exit(-1);
             });
@@ -82,7 +398,7 @@ fn clean_console() {
         std::process::Command::new("clear").status().unwrap_or_else(|status| {
             println!(
                 "An error occurred while clearing the screen: {}, exit.",
-                status.to_string()
+                status
             );
             exit(-1);
         });
@@ -91,34 +407,110 @@ fn clean_console() {
 
 fn main() {
     let mut exit_app = false;
-    let todos: Todos = match load() {
-        Ok(todos) => todos,
+    let mut args: Vec<String> = std::env::args().skip(1).collect();
+    let format = match cli::take_format(&mut args) {
+        Ok(format) => format,
+        Err(err) => {
+            print!("{}", err.render(OutputFormat::Text));
+            exit(-1);
+        }
+    };
+    let command = match cli::parse(&args) {
+        Ok(None) if format != OutputFormat::Text => {
+            Err(cli::Error::Usage("--format needs a command".to_string()))
+        }
+        result => result,
+    };
+    let command = match command {
+        Ok(command) => command,
         Err(err) => {
-            println!("Error loading data from file: {}", err.to_string());
-            Arc::new(RefCell::new(Vec::new()))
+            print!("{}", err.render(format));
+            exit(-1);
+        }
+    };
+
+    let loaded = Config::load(Path::new(CONFIG_FILE)).and_then(|config| {
+        let templates = config.templates.templates()?;
+        Ok((config, templates))
+    });
+    let (config, templates) = match loaded {
+        Ok(loaded) => loaded,
+        Err(err) if command.is_some() => {
+            print!("{}", cli::Error::from(err).render(format));
+            exit(-1);
+        }
+        Err(err) => {
+            println!("{}, exit.", err);
+            exit(-1);
+        }
+    };
+
+    if let Some(command) = command {
+        let result = match command {
+            Command::Doctor { dry_run } => run_doctor(&config, dry_run).map_err(Into::into),
+            command => run_command(&config, templates.clone(), &command),
+        };
+        match result {
+            Ok(outcome) => {
+                print!("{}", outcome.render(format, &templates));
+                let failed = matches!(
+                    outcome,
+                    Outcome::Doctor {
+                        repairable: false,
+                        ..
+                    }
+                );
+                exit(if failed { -1 } else { 0 });
+            }
+            Err(err) => {
+                print!("{}", err.render(format));
+                exit(-1);
+            }
+        }
+    }
+
+    // Backends without a path have nothing to lock
+    let lock = config.storage.path().map(acquire_lock);
+    let read_only = matches!(lock, Some(None));
+
+    let mut session = match open_session(&config, templates, read_only) {
+        Ok(session) => session,
+        Err(err) => {
+            println!("{}, exit.", err.0);
+            exit(-1);
         }
     };
 
     while !exit_app {
         clean_console();
-        print_main();
+        print_main(read_only);
 
         let input = match get_input() {
             Ok(input) => input,
             Err(err) => {
-                println!("Error when reading input: {}, exit.", err.to_string());
+                println!("Error when reading input: {}, exit.", err);
                 exit(-1);
             }
         };
 
         let action = Action::from(input);
-        execute_action(&mut exit_app, &todos, action);
+        // Syncs store their changes themselves
+        let before = (!action.stores_changes()).then(|| session.todos.borrow().clone());
+        execute_action(&mut exit_app, &mut session, action);
+        if let Some(before) = before {
+            session.record_changes(&before);
+        }
     }
 
-    println!("Storing to file....");
-    if let Err(err) = store(todos) {
-        println!("Error storing data to file: {}", err.to_string());
+    if read_only {
+        println!("Read-only mode, nothing is stored.");
+    } else {
+        println!("Storing data....");
+        if let Err(err) = session.store().and_then(|_| session.commit()) {
+            println!("Error storing data: {}", err);
+        }
     }
+    drop(lock); // `exit` skips destructors
     println!("Exiting, Bye!");
     std::process::exit(0);
 }